@@ -0,0 +1,161 @@
+use crate::client::BiliClient;
+use crate::error::DilidiliError;
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use serde::Deserialize;
+
+// B站接口：返回原始响应正文，由 video、auth 模块统一解析
+// BiliClient 请求线上接口，fixture::FixtureApi 回放录制的响应用于离线测试
+pub trait BilibiliApi: Send + Sync {
+    // 登录用户信息，未登录时同样返回 WBI 密钥
+    fn nav(&self) -> BoxFuture<'_, Result<String, DilidiliError>>;
+
+    // 视频信息
+    fn view<'a>(&'a self, bvid: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>>;
+
+    // 视频流地址
+    fn playurl<'a>(&'a self, bvid: &'a str, cid: u64) -> BoxFuture<'a, Result<String, DilidiliError>>;
+
+    // 生成登录二维码
+    fn qrcode_generate(&self) -> BoxFuture<'_, Result<String, DilidiliError>>;
+
+    // 轮询二维码扫描状态
+    fn qrcode_poll<'a>(&'a self, qrcode_key: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>>;
+
+    // 检查登录 cookie 是否需要刷新
+    fn cookie_info<'a>(&'a self, csrf: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>>;
+
+    // correspond 页面（HTML），其中带有 refresh_csrf
+    fn correspond<'a>(&'a self, correspond_path: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>>;
+
+    // 刷新 cookie，新的 cookie 通过 Set-Cookie 写入
+    fn cookie_refresh<'a>(&'a self, csrf: &'a str, refresh_csrf: &'a str, refresh_token: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>>;
+
+    // 确认刷新，使旧的 refresh_token 失效
+    fn confirm_refresh<'a>(&'a self, csrf: &'a str, refresh_token: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>>;
+
+    // 当前的登录 cookie，Cookie 请求头格式
+    fn cookies(&self) -> String;
+
+    // 下载媒体流使用的客户端
+    fn media(&self) -> reqwest::Client;
+}
+
+// 只解析响应中的 code
+#[derive(Deserialize)]
+struct ApiCode {
+    code: i32,
+}
+
+impl BiliClient {
+    async fn get(&self, url: &str) -> Result<String, DilidiliError> {
+        crate::ratelimit::send(self.api().get(url)).await
+    }
+
+    async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<String, DilidiliError> {
+        crate::ratelimit::send(self.api().post(url).form(form)).await
+    }
+
+    // 请求带 WBI 签名的接口，签名被拒绝时刷新密钥重试一次
+    async fn get_signed(&self, base: &str, params: &[(&str, String)]) -> Result<String, DilidiliError> {
+        let text = self.get(&crate::wbi::signed_url(self, base, params).await?).await?;
+
+        let code = serde_json::from_str::<ApiCode>(&text).map(|body| body.code).unwrap_or(0);
+        if !crate::wbi::is_signature_rejected(code) {
+            return Ok(text);
+        }
+
+        println!("⚠️ WBI 签名被拒绝 ({})，刷新密钥后重试", code);
+        crate::wbi::invalidate();
+        self.get(&crate::wbi::signed_url(self, base, params).await?).await
+    }
+}
+
+impl BilibiliApi for BiliClient {
+    fn nav(&self) -> BoxFuture<'_, Result<String, DilidiliError>> {
+        self.get("https://api.bilibili.com/x/web-interface/nav").boxed()
+    }
+
+    fn view<'a>(&'a self, bvid: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        async move {
+            self.get_signed("https://api.bilibili.com/x/web-interface/wbi/view", &[("bvid", bvid.to_string())]).await
+        }
+        .boxed()
+    }
+
+    fn playurl<'a>(&'a self, bvid: &'a str, cid: u64) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        async move {
+            self.get_signed(
+                "https://api.bilibili.com/x/player/wbi/playurl",
+                &[
+                    ("bvid", bvid.to_string()),
+                    ("cid", cid.to_string()),
+                    ("qn", "127".to_string()),
+                    ("fourk", "1".to_string()),
+                    ("fnval", "4048".to_string()),
+                    ("fnver", "0".to_string()),
+                ],
+            )
+            .await
+        }
+        .boxed()
+    }
+
+    fn qrcode_generate(&self) -> BoxFuture<'_, Result<String, DilidiliError>> {
+        self.get("https://passport.bilibili.com/x/passport-login/web/qrcode/generate").boxed()
+    }
+
+    fn qrcode_poll<'a>(&'a self, qrcode_key: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        async move {
+            // 登录成功时 Set-Cookie 直接写入共享的 cookie jar
+            let url = format!("https://passport.bilibili.com/x/passport-login/web/qrcode/poll?qrcode_key={}", qrcode_key);
+            self.get(&url).await
+        }
+        .boxed()
+    }
+
+    fn cookie_info<'a>(&'a self, csrf: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        async move {
+            let url = format!("https://passport.bilibili.com/x/passport-login/web/cookie/info?csrf={}", csrf);
+            self.get(&url).await
+        }
+        .boxed()
+    }
+
+    fn correspond<'a>(&'a self, correspond_path: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        async move {
+            self.get(&format!("https://www.bilibili.com/correspond/1/{}", correspond_path)).await
+        }
+        .boxed()
+    }
+
+    fn cookie_refresh<'a>(&'a self, csrf: &'a str, refresh_csrf: &'a str, refresh_token: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        async move {
+            self.post_form(
+                "https://passport.bilibili.com/x/passport-login/web/cookie/refresh",
+                &[("csrf", csrf), ("refresh_csrf", refresh_csrf), ("source", "main_web"), ("refresh_token", refresh_token)],
+            )
+            .await
+        }
+        .boxed()
+    }
+
+    fn confirm_refresh<'a>(&'a self, csrf: &'a str, refresh_token: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        async move {
+            self.post_form(
+                "https://passport.bilibili.com/x/passport-login/web/confirm/refresh",
+                &[("csrf", csrf), ("refresh_token", refresh_token)],
+            )
+            .await
+        }
+        .boxed()
+    }
+
+    fn cookies(&self) -> String {
+        self.cookie_jar().header()
+    }
+
+    fn media(&self) -> reqwest::Client {
+        BiliClient::media(self)
+    }
+}
@@ -1,116 +1,223 @@
+use crate::api::BilibiliApi;
+use crate::client::BiliClient;
+use crate::error::DilidiliError;
 use crate::types::*;
-use reqwest::cookie::{Jar, CookieStore};
-use std::sync::Arc;
-use url;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::async_runtime::JoinHandle;
+use tauri::{Emitter, Manager};
 
-// 用户代理
-const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+// 扫码登录状态事件名
+pub const LOGIN_STATUS_EVENT: &str = "login-status";
 
-// 获取登录二维码
-#[tauri::command]
-pub async fn get_login_qr_code() -> Result<QrCodeData, String> {
-    let client = reqwest::Client::new();
-    let url = "https://passport.bilibili.com/x/passport-login/web/qrcode/generate";
-    
-    let res = client.get(url)
-        .header("User-Agent", USER_AGENT)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if res.status().is_success() {
-        let body = res.json::<QrCodeResponse>().await.map_err(|e| e.to_string())?;
-        Ok(body.data)
-    } else {
-        Err(format!("Failed to get QR code: {}", res.status()))
+// 轮询二维码状态的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// 二维码连续过期这么多次后不再自动刷新，避免无人值守时一直轮询
+const MAX_QR_CODES: u32 = 5;
+
+// 轮询连续失败这么多次后放弃
+const MAX_POLL_FAILURES: u32 = 3;
+
+// 二维码轮询返回的 data.code
+const QR_CONFIRMED: i32 = 0;
+const QR_EXPIRED: i32 = 86038;
+const QR_SCANNED: i32 = 86090;
+const QR_WAITING_SCAN: i32 = 86101;
+
+// 进行中的扫码登录，同一时间只保留一个
+#[derive(Default)]
+pub struct LoginSession {
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl LoginSession {
+    // 替换当前的轮询任务，旧任务直接中止
+    fn replace(&self, task: Option<JoinHandle<()>>) {
+        if let Some(old) = std::mem::replace(&mut *self.task.lock().unwrap(), task) {
+            old.abort();
+        }
     }
 }
 
-// 轮询登录状态
+pub async fn fetch_qr_code(api: &dyn BilibiliApi) -> Result<QrCodeData, DilidiliError> {
+    let text = api.qrcode_generate().await?;
+    let body: QrCodeResponse = serde_json::from_str(&text)?;
+    Ok(body.data)
+}
+
+pub async fn fetch_poll_status(api: &dyn BilibiliApi, qrcode_key: &str) -> Result<PollData, DilidiliError> {
+    let text = api.qrcode_poll(qrcode_key).await?;
+    let poll_resp: PollResponse = serde_json::from_str(&text)?;
+    Ok(poll_resp.data)
+}
+
+// 新二维码的 WaitingScan 状态，同时在终端打印二维码，没有界面时也能扫码
+fn waiting_scan(qrcode: &QrCodeData, options: &QrRenderOptions) -> Result<LoginStatus, DilidiliError> {
+    let image = crate::qr::render_image(&qrcode.url, options)?;
+    println!("请使用哔哩哔哩App扫码登录:\n{}", crate::qr::render_terminal(&qrcode.url, options.ec_level)?);
+    Ok(LoginStatus::WaitingScan { qrcode: qrcode.clone(), image })
+}
+
+// 开始扫码登录：返回第一个二维码，之后在后台轮询并通过 login-status 事件推送状态
+// options 决定事件中二维码图片的尺寸和纠错等级
 #[tauri::command]
-pub async fn poll_login_status(qrcode_key: String) -> Result<LoginSuccessData, String> {
-    let cookie_jar = Arc::new(Jar::default());
-    let client = reqwest::Client::builder()
-        .cookie_provider(cookie_jar.clone())
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let url = format!("https://passport.bilibili.com/x/passport-login/web/qrcode/poll?qrcode_key={}", qrcode_key);
-    let request_url = url::Url::parse(&url).map_err(|e| e.to_string())?;
-
-    let res = client.get(&url)
-        .header("User-Agent", USER_AGENT)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if res.status().is_success() {
-        let mut cookies_str = String::new();
-        if let Some(cookie_header) = cookie_jar.cookies(&request_url) {
-            cookies_str = cookie_header.to_str().unwrap_or("").to_string();
-        }
-        
-        let poll_resp = res.json::<PollResponse>().await.map_err(|e| e.to_string())?;
+pub async fn start_qr_login(
+    app_handle: tauri::AppHandle,
+    client: tauri::State<'_, BiliClient>,
+    session: tauri::State<'_, LoginSession>,
+    options: Option<QrRenderOptions>
+) -> Result<QrCodeData, DilidiliError> {
+    session.replace(None);
 
-        Ok(LoginSuccessData {
-            poll_data: poll_resp.data,
-            cookies: cookies_str,
-        })
-    } else {
-        Err(format!("Polling failed: {}", res.status()))
+    // 使用单独的 cookie jar 登录，添加账号时不影响当前账号
+    let login_client = client.with_cookies("")?;
+    let options = options.unwrap_or_default();
+    let qrcode = fetch_qr_code(&login_client).await?;
+    let _ = app_handle.emit(LOGIN_STATUS_EVENT, waiting_scan(&qrcode, &options)?);
+
+    let task = tauri::async_runtime::spawn(run_qr_login(app_handle.clone(), login_client, qrcode.clone(), options));
+    session.replace(Some(task));
+    Ok(qrcode)
+}
+
+// 取消扫码登录（关闭登录窗口时）
+#[tauri::command]
+pub async fn cancel_qr_login(session: tauri::State<'_, LoginSession>) -> Result<(), DilidiliError> {
+    session.replace(None);
+    Ok(())
+}
+
+// 轮询二维码状态直到登录成功、失败或二维码多次过期
+async fn run_qr_login(app_handle: tauri::AppHandle, client: BiliClient, mut qrcode: QrCodeData, options: QrRenderOptions) {
+    let emit = |status: LoginStatus| {
+        let _ = app_handle.emit(LOGIN_STATUS_EVENT, status);
+    };
+    let fail = |error: DilidiliError| {
+        println!("❌ 扫码登录失败: {}", error);
+        emit(LoginStatus::Failed { error: (&error).into() });
+    };
+
+    let mut generated = 1;
+    let mut failures = 0;
+    let mut scanned = false;
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        let poll = match fetch_poll_status(&client, &qrcode.qrcode_key).await {
+            Ok(poll) => poll,
+            Err(e) if e.retryable() && failures + 1 < MAX_POLL_FAILURES => {
+                failures += 1;
+                println!("⚠️ 轮询登录状态失败 ({}/{}): {}", failures, MAX_POLL_FAILURES, e);
+                continue;
+            }
+            Err(e) => return fail(e),
+        };
+        failures = 0;
+
+        match poll.code {
+            QR_WAITING_SCAN => {}
+            QR_SCANNED => {
+                if !scanned {
+                    scanned = true;
+                    emit(LoginStatus::Scanned);
+                }
+            }
+            QR_EXPIRED => {
+                let regenerating = generated < MAX_QR_CODES;
+                println!("二维码已过期，{}", if regenerating { "重新生成" } else { "停止轮询" });
+                emit(LoginStatus::Expired { regenerating });
+                if !regenerating {
+                    return;
+                }
+
+                qrcode = match fetch_qr_code(&client).await {
+                    Ok(qrcode) => qrcode,
+                    Err(e) => return fail(e),
+                };
+                generated += 1;
+                scanned = false;
+                match waiting_scan(&qrcode, &options) {
+                    Ok(status) => emit(status),
+                    Err(e) => return fail(e),
+                }
+            }
+            QR_CONFIRMED => {
+                match finish_login(&app_handle, &client, poll.refresh_token).await {
+                    Ok(login_data) => emit(LoginStatus::Confirmed { login_info: (&login_data).into() }),
+                    Err(e) => fail(e),
+                }
+                return;
+            }
+            code => return fail(DilidiliError::Api { code, message: poll.message }),
+        }
     }
 }
 
+// 登录成功：cookie 已由 Set-Cookie 写入登录用的 jar，获取用户信息后保存为账号并切换过去
+async fn finish_login(app_handle: &tauri::AppHandle, client: &BiliClient, refresh_token: Option<String>) -> Result<StoredLoginData, DilidiliError> {
+    println!("✅ 扫码登录成功，获取用户信息");
+    let user_profile = match fetch_user_info(client).await {
+        Ok(profile) => Some(profile),
+        Err(e) => {
+            println!("⚠️ 获取用户信息失败: {}，仍然保存登录状态", e);
+            None
+        }
+    };
+
+    let login_data = StoredLoginData {
+        cookies: client.cookie_jar().header(),
+        user_profile,
+        login_time: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+        refresh_token,
+    };
+    crate::storage::add_login_profile(app_handle, &login_data)?;
+    app_handle.state::<BiliClient>().cookie_jar().load(&login_data.cookies);
+    Ok(login_data)
+}
+
 // 获取用户信息
 #[tauri::command]
-pub async fn get_user_info(cookies: String) -> Result<UserProfile, String> {
-    println!("获取用户信息，cookies长度: {}", cookies.len());
-    
-    let client = reqwest::Client::new();
-    let url = "https://api.bilibili.com/x/web-interface/nav";
+pub async fn get_user_info(client: tauri::State<'_, BiliClient>) -> Result<UserProfile, DilidiliError> {
+    println!("获取用户信息，cookies长度: {}", client.cookie_jar().header().len());
+    fetch_user_info(client.inner()).await
+}
+
+pub async fn fetch_user_info(api: &dyn BilibiliApi) -> Result<UserProfile, DilidiliError> {
+    let body_text = api.nav()
+    .await
+    .map_err(|e| {
+        println!("请求失败: {}", e);
+        e
+    })?;
+    println!("API响应: {}", body_text);
     
-    let res = client.get(url)
-        .header("User-Agent", USER_AGENT)
-        .header("Cookie", cookies)
-        .header("Referer", "https://www.bilibili.com/")
-        .send()
-        .await
+    let body: UserInfoResponse = serde_json::from_str(&body_text)
         .map_err(|e| {
-            println!("请求失败: {}", e);
-            e.to_string()
+            println!("JSON解析失败: {}", e);
+            DilidiliError::from(e)
         })?;
+    
+    if body.code == 0 {
+        // 顺便缓存 WBI 签名密钥
+        if let Some(wbi_img) = &body.data.wbi_img {
+            crate::wbi::remember_keys(wbi_img);
+        }
 
-    println!("响应状态: {}", res.status());
-
-    if res.status().is_success() {
-        let body_text = res.text().await.map_err(|e| e.to_string())?;
-        println!("API响应: {}", body_text);
-        
-        let body: UserInfoResponse = serde_json::from_str(&body_text)
-            .map_err(|e| {
-                println!("JSON解析失败: {}", e);
-                e.to_string()
-            })?;
+        // vip_status: 0=非大会员, 1=大会员
+        let vip_status = body.data.vip_status;
+        println!("VIP状态: vip_status={}, vip_type={}", vip_status, body.data.vip_type);
         
-        if body.code == 0 {
-            // vip_status: 0=非大会员, 1=大会员
-            let vip_status = body.data.vip_status;
-            println!("VIP状态: vip_status={}, vip_type={}", vip_status, body.data.vip_type);
-            
-            let user_profile = UserProfile {
-                name: body.data.uname,
-                avatar: body.data.face,
-                mid: body.data.mid,
-                vip_type: vip_status, // 使用vip_status而不是vip.vip_type
-            };
-            println!("用户信息获取成功: {:?}", user_profile);
-            Ok(user_profile)
-        } else {
-            println!("API返回错误: code={}, message={}", body.code, body.message);
-            Err(format!("Failed to get user info: {}", body.message))
-        }
+        let user_profile = UserProfile {
+            name: body.data.uname,
+            avatar: body.data.face,
+            mid: body.data.mid,
+            vip_type: vip_status, // 使用vip_status而不是vip.vip_type
+        };
+        println!("用户信息获取成功: {:?}", user_profile);
+        Ok(user_profile)
     } else {
-        println!("HTTP请求失败: {}", res.status());
-        Err(format!("Failed to get user info: {}", res.status()))
+        println!("API返回错误: code={}, message={}", body.code, body.message);
+        Err(DilidiliError::Api { code: body.code, message: body.message })
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,105 @@
+use crate::error::DilidiliError;
+use crate::types::*;
+
+// BV 号与 av 号互转，算法与 B站前端一致（2^51 版本，兼容旧的小 av 号）
+const XOR_CODE: u64 = 23442827791579;
+const MASK_CODE: u64 = 2251799813685247;
+const MAX_AID: u64 = 1 << 51;
+const BASE: u64 = 58;
+const ALPHABET: &[u8; 58] = b"FcwAPNKTMug3GV5Lj7EJnHpWsx4tb8haYeviqBz6rkCy12mUSDQX9RdoZf";
+
+// BV 号固定 12 位，编码部分从第 4 位开始，并交换 3↔9、4↔7 两组位置
+const BVID_LEN: usize = 12;
+const PREFIX_LEN: usize = 3;
+
+// 不区分大小写的前缀判断
+fn has_prefix(value: &str, prefix: &str) -> bool {
+    value.get(..prefix.len()).is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+}
+
+pub fn av_to_bv(aid: u64) -> Result<String, DilidiliError> {
+    if aid == 0 || aid >= MAX_AID {
+        return Err(DilidiliError::InvalidInput(format!("av 号超出范围: {}", aid)));
+    }
+
+    let mut bytes = *b"BV1000000000";
+    let mut tmp = (MAX_AID | aid) ^ XOR_CODE;
+    let mut index = BVID_LEN - 1;
+    while tmp > 0 && index >= PREFIX_LEN {
+        bytes[index] = ALPHABET[(tmp % BASE) as usize];
+        tmp /= BASE;
+        index -= 1;
+    }
+    bytes.swap(3, 9);
+    bytes.swap(4, 7);
+
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+pub fn bv_to_av(bvid: &str) -> Result<u64, DilidiliError> {
+    let bytes = bvid.as_bytes();
+    if bytes.len() != BVID_LEN || !has_prefix(bvid, "BV") {
+        return Err(DilidiliError::InvalidInput(format!("无效的 BV 号: {}", bvid)));
+    }
+
+    let mut bytes = bytes.to_vec();
+    bytes.swap(3, 9);
+    bytes.swap(4, 7);
+
+    let mut tmp: u64 = 0;
+    for &c in &bytes[PREFIX_LEN..] {
+        let digit = ALPHABET.iter()
+            .position(|&a| a == c)
+            .ok_or_else(|| DilidiliError::InvalidInput(format!("无效的 BV 号: {}", bvid)))?;
+        tmp = tmp.checked_mul(BASE)
+            .and_then(|t| t.checked_add(digit as u64))
+            .ok_or_else(|| DilidiliError::InvalidInput(format!("无效的 BV 号: {}", bvid)))?;
+    }
+
+    Ok((tmp & MASK_CODE) ^ XOR_CODE)
+}
+
+// 解析 "BV..."、"av123" 或纯数字 av 号，返回两种形式
+pub fn video_ids(id: &str) -> Result<VideoIds, DilidiliError> {
+    let id = id.trim();
+    let aid = if has_prefix(id, "BV") {
+        bv_to_av(id)?
+    } else {
+        let digits = if has_prefix(id, "av") { &id[2..] } else { id };
+        digits.parse::<u64>().map_err(|_| DilidiliError::InvalidInput(format!("无效的视频 ID: {}", id)))?
+    };
+
+    Ok(VideoIds { aid, bvid: av_to_bv(aid)? })
+}
+
+// 统一使用 BV 号作为视频的标识
+pub fn canonical_bvid(id: &str) -> Result<String, DilidiliError> {
+    video_ids(id).map(|ids| ids.bvid)
+}
+
+// BV 号与 av 号互转
+#[tauri::command]
+pub async fn convert_video_id(id: String) -> Result<VideoIds, DilidiliError> {
+    video_ids(&id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_known_ids() {
+        assert_eq!(av_to_bv(170001).unwrap(), "BV17x411w7KC");
+        assert_eq!(bv_to_av("BV17x411w7KC").unwrap(), 170001);
+        assert_eq!(canonical_bvid("av170001").unwrap(), "BV17x411w7KC");
+        assert_eq!(canonical_bvid("bv17x411w7KC").unwrap(), "BV17x411w7KC");
+    }
+
+    #[test]
+    fn rejects_invalid_ids() {
+        assert!(av_to_bv(0).is_err());
+        assert!(av_to_bv(MAX_AID).is_err());
+        assert!(bv_to_av("BV17x411w7K").is_err());
+        assert!(bv_to_av("BV17x411w7K0").is_err());
+    }
+}
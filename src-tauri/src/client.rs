@@ -0,0 +1,251 @@
+use crate::error::DilidiliError;
+use crate::types::*;
+use reqwest::cookie::CookieStore;
+use reqwest::header::{HeaderMap, HeaderValue, REFERER};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 用户代理
+pub const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+
+// API 请求的总超时
+const API_TIMEOUT: Duration = Duration::from_secs(30);
+
+// 媒体流耗时不定，只限制连接和单次读取的超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Set-Cookie 修改了 cookie 后的回调，参数为新的 Cookie 请求头
+type ChangeListener = Box<dyn Fn(&str) + Send + Sync>;
+
+// 登录 cookie，只发送给 bilibili.com 及其子域名，CDN 地址自带签名不需要 cookie
+#[derive(Default)]
+pub struct CookieJar {
+    cookies: RwLock<BTreeMap<String, String>>,
+    on_change: RwLock<Option<ChangeListener>>,
+}
+
+fn is_bilibili_host(url: &url::Url) -> bool {
+    url.host_str().is_some_and(|host| host == "bilibili.com" || host.ends_with(".bilibili.com"))
+}
+
+impl CookieJar {
+    // 用 "SESSDATA=xxx; bili_jct=yyy" 形式的字符串替换全部 cookie
+    pub fn load(&self, cookie_str: &str) {
+        let mut cookies = self.cookies.write().unwrap();
+        cookies.clear();
+        for pair in cookie_str.split(';') {
+            if let Some((name, value)) = pair.trim().split_once('=') {
+                cookies.insert(name.trim().to_string(), value.trim().to_string());
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        self.cookies.write().unwrap().clear();
+    }
+
+    // 服务器通过 Set-Cookie 修改 cookie 时调用（用于写回账号数据），load/clear 不触发
+    pub fn on_change(&self, listener: impl Fn(&str) + Send + Sync + 'static) {
+        *self.on_change.write().unwrap() = Some(Box::new(listener));
+    }
+
+    // 拼接为 Cookie 请求头的格式
+    pub fn header(&self) -> String {
+        self.cookies.read().unwrap()
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+impl CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &url::Url) {
+        if !is_bilibili_host(url) {
+            return;
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        let mut changed = false;
+        {
+            let mut cookies = self.cookies.write().unwrap();
+            for header in cookie_headers {
+                let Ok(header) = header.to_str() else { continue };
+                let Ok(cookie) = cookie::Cookie::parse(header) else { continue };
+                let (name, value) = (cookie.name(), cookie.value());
+
+                // 退出登录时服务器用空值、Max-Age=0 或过去的 Expires 删除 cookie
+                let removed = value.is_empty()
+                    || cookie.max_age().is_some_and(|age| age.whole_seconds() <= 0)
+                    || cookie.expires_datetime().is_some_and(|expires| expires.unix_timestamp() <= now);
+                if removed {
+                    changed |= cookies.remove(name).is_some();
+                } else {
+                    changed |= cookies.insert(name.to_string(), value.to_string()).as_deref() != Some(value);
+                }
+            }
+        }
+
+        if changed {
+            if let Some(listener) = self.on_change.read().unwrap().as_ref() {
+                listener(&self.header());
+            }
+        }
+    }
+
+    fn cookies(&self, url: &url::Url) -> Option<HeaderValue> {
+        if !is_bilibili_host(url) {
+            return None;
+        }
+        let header = self.header();
+        if header.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+// 由代理设置生成 reqwest 代理，用户名和密码放在 URL 中，HTTP 和 SOCKS5 代理都能识别
+fn build_proxy(settings: &ProxySettings) -> Result<reqwest::Proxy, DilidiliError> {
+    let host = settings.host.trim();
+    if host.is_empty() || settings.port == 0 {
+        return Err(DilidiliError::InvalidInput("代理地址或端口为空".to_string()));
+    }
+
+    // socks5h 由代理解析域名，按代理所在地区选择 CDN 节点
+    let scheme = match settings.scheme {
+        ProxyScheme::Http => "http",
+        ProxyScheme::Https => "https",
+        ProxyScheme::Socks5 => "socks5h",
+    };
+    let mut url = url::Url::parse(&format!("{}://{}:{}", scheme, host, settings.port))
+        .map_err(|e| DilidiliError::InvalidInput(format!("代理地址无效: {}", e)))?;
+    if let Some(username) = settings.username.as_deref().filter(|u| !u.is_empty()) {
+        url.set_username(username)
+            .and_then(|_| url.set_password(settings.password.as_deref()))
+            .map_err(|_| DilidiliError::InvalidInput("代理用户名或密码无效".to_string()))?;
+    }
+
+    let proxy = reqwest::Proxy::all(url.as_str())
+        .map_err(|e| DilidiliError::InvalidInput(format!("代理地址无效: {}", e)))?;
+    Ok(proxy.no_proxy(reqwest::NoProxy::from_string(&settings.bypass.join(","))))
+}
+
+// 两类请求使用的 reqwest::Client，修改代理设置时整体替换
+struct Clients {
+    api: reqwest::Client,   // api.bilibili.com、passport 等接口
+    media: reqwest::Client, // CDN 媒体流
+}
+
+impl Clients {
+    fn build(jar: &Arc<CookieJar>, proxy: &ProxySettings) -> Result<Self, DilidiliError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(REFERER, HeaderValue::from_static("https://www.bilibili.com/"));
+
+        let mut api = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .default_headers(headers.clone())
+            .cookie_provider(jar.clone())
+            .timeout(API_TIMEOUT);
+        if proxy.api {
+            api = api.proxy(build_proxy(proxy)?);
+        }
+
+        let mut media = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .default_headers(headers)
+            .cookie_provider(jar.clone())
+            .connect_timeout(CONNECT_TIMEOUT)
+            .read_timeout(READ_TIMEOUT);
+        if proxy.media {
+            media = media.proxy(build_proxy(proxy)?);
+        }
+
+        Ok(Self { api: api.build()?, media: media.build()? })
+    }
+}
+
+// 所有模块共用的 B站 客户端，作为 Tauri managed state 注册
+// reqwest::Client 内部带连接池，克隆 BiliClient 只复制引用
+#[derive(Clone)]
+pub struct BiliClient {
+    jar: Arc<CookieJar>,
+    clients: Arc<RwLock<Clients>>,
+    proxy: Arc<RwLock<ProxySettings>>,
+}
+
+impl BiliClient {
+    pub fn new(cookies: &str, proxy: &ProxySettings) -> Result<Self, DilidiliError> {
+        let jar = Arc::new(CookieJar::default());
+        jar.load(cookies);
+        let clients = Clients::build(&jar, proxy)?;
+        Ok(Self {
+            jar,
+            clients: Arc::new(RwLock::new(clients)),
+            proxy: Arc::new(RwLock::new(proxy.clone())),
+        })
+    }
+
+    // 代理设置相同、cookie 独立的客户端，用于当前账号以外的账号
+    pub fn with_cookies(&self, cookies: &str) -> Result<Self, DilidiliError> {
+        Self::new(cookies, &self.proxy.read().unwrap())
+    }
+
+    // 按新的代理设置重建客户端，进行中的下载继续使用原来的连接
+    pub fn set_proxy(&self, proxy: &ProxySettings) -> Result<(), DilidiliError> {
+        let clients = Clients::build(&self.jar, proxy)?;
+        *self.clients.write().unwrap() = clients;
+        *self.proxy.write().unwrap() = proxy.clone();
+        println!("代理设置: API {}，媒体流 {}", proxy.api, proxy.media);
+        Ok(())
+    }
+
+    pub fn api(&self) -> reqwest::Client {
+        self.clients.read().unwrap().api.clone()
+    }
+
+    pub fn media(&self) -> reqwest::Client {
+        self.clients.read().unwrap().media.clone()
+    }
+
+    pub fn cookie_jar(&self) -> &CookieJar {
+        &self.jar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn set(jar: &CookieJar, headers: &[&str]) {
+        let url = url::Url::parse("https://passport.bilibili.com/").unwrap();
+        let values: Vec<_> = headers.iter().map(|h| HeaderValue::from_str(h).unwrap()).collect();
+        jar.set_cookies(&mut values.iter(), &url);
+    }
+
+    #[test]
+    fn set_cookie_updates_and_expires() {
+        let jar = CookieJar::default();
+        jar.load("SESSDATA=old; bili_jct=csrf; DedeUserID=1");
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let seen = changes.clone();
+        jar.on_change(move |header| seen.lock().unwrap().push(header.to_string()));
+
+        set(&jar, &["SESSDATA=new; Path=/; Domain=.bilibili.com; HttpOnly"]);
+        set(&jar, &["bili_jct=csrf; Path=/"]);
+        set(&jar, &[
+            "DedeUserID=; Max-Age=0",
+            "bili_jct=csrf; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+        ]);
+
+        // 值没变的 Set-Cookie 不触发回调
+        assert_eq!(jar.header(), "SESSDATA=new");
+        assert_eq!(*changes.lock().unwrap(), vec![
+            "DedeUserID=1; SESSDATA=new; bili_jct=csrf".to_string(),
+            "SESSDATA=new".to_string(),
+        ]);
+    }
+}
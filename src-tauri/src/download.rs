@@ -1,183 +1,1146 @@
+use crate::api::BilibiliApi;
+use crate::error::DilidiliError;
 use crate::types::*;
-use std::io::Write;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use tauri::Manager;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Manager};
+use tokio::io::AsyncWriteExt;
 
-// 用户代理
-const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+// 下载进度事件名
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "download-progress";
 
-// 下载视频
-#[tauri::command]
-pub async fn download_video(
-    app_handle: tauri::AppHandle,
-    video_data: VideoData,
-    video_stream: VideoStream,
-    audio_stream: AudioStream,
-    cookies: String
-) -> Result<String, String> {
-    println!("=== 开始下载视频 ===");
-    println!("视频标题: {}", video_data.title);
-    println!("视频质量: {}", video_stream.description);
-    println!("视频URL: {:?}", video_stream.url);
-    println!("音频URL: {:?}", audio_stream.url);
-    println!("Cookies长度: {}", cookies.len());
-    
-    // 创建下载目录
-    let downloads_dir = app_handle.path().download_dir()
-        .map_err(|e| e.to_string())?
-        .join("DILIDILI");
-    
-    fs::create_dir_all(&downloads_dir).map_err(|e| e.to_string())?;
-    
-    // 安全的文件名
-    let safe_title = video_data.title
-        .chars()
-        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' { c } else { '_' })
-        .collect::<String>();
-    
-    let video_filename = format!("{}_video.mp4", safe_title);
-    let audio_filename = format!("{}_audio.mp3", safe_title);
-    let final_filename = format!("{}.mp4", safe_title);
-    
-    let video_path = downloads_dir.join(&video_filename);
-    let audio_path = downloads_dir.join(&audio_filename);
-    let final_path = downloads_dir.join(&final_filename);
-
-    // 创建带超时的客户端
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(300)) // 5分钟超时
-        .user_agent(USER_AGENT)
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    // 下载视频流
-    if let Some(video_url) = &video_stream.url {
-        println!("=== 开始下载视频流 ===");
-        println!("视频URL: {}", video_url);
-        println!("保存到: {:?}", video_path);
-        
-        let response = client.get(video_url)
-            .header("Cookie", &cookies)
-            .header("Referer", &format!("https://www.bilibili.com/video/{}", video_data.bvid))
-            .header("Origin", "https://www.bilibili.com")
-            .header("Accept", "*/*")
-            .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
-            .header("Accept-Encoding", "gzip, deflate, br")
-            .header("Connection", "keep-alive")
-            .header("Sec-Fetch-Dest", "empty")
-            .header("Sec-Fetch-Mode", "cors")
-            .header("Sec-Fetch-Site", "cross-site")
-            .send()
-            .await
-            .map_err(|e| format!("视频流请求失败: {}", e))?;
+// 两次进度事件之间的最小间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
 
-        println!("视频流响应状态: {}", response.status());
-        println!("视频流响应头: {:?}", response.headers());
+// 超过该时间没有收到数据视为连接卡住
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
 
-        let status = response.status();
-        if status.is_success() {
-            let content = response.bytes().await.map_err(|e| format!("读取视频流内容失败: {}", e))?;
-            println!("视频流大小: {} bytes", content.len());
-            
-            let mut file = std::fs::File::create(&video_path).map_err(|e| format!("创建视频文件失败: {}", e))?;
-            file.write_all(&content).map_err(|e| format!("写入视频文件失败: {}", e))?;
-            println!("✅ 视频流下载完成");
-        } else {
-            let error_text = response.text().await.unwrap_or_else(|_| "无法读取错误信息".to_string());
-            return Err(format!("视频流下载失败: {} - {}", status, error_text));
+// 构造带 B站 CDN 所需请求头的媒体流请求
+fn media_request(client: &reqwest::Client, url: &str, bvid: &str) -> reqwest::RequestBuilder {
+    client.get(url)
+        .header("Referer", format!("https://www.bilibili.com/video/{}", bvid))
+        .header("Origin", "https://www.bilibili.com")
+        .header("Accept", "*/*")
+        .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
+        // 按原始字节计数和落盘，不接受压缩编码
+        .header("Accept-Encoding", "identity")
+        .header("Connection", "keep-alive")
+        .header("Sec-Fetch-Dest", "empty")
+        .header("Sec-Fetch-Mode", "cors")
+        .header("Sec-Fetch-Site", "cross-site")
+}
+
+// 单个流的进度统计，负责计算速度和剩余时间
+struct ProgressTracker {
+    task_id: String,
+    kind: StreamKind,
+    total: Option<u64>,
+    downloaded: u64,
+    speed: f64,
+    last_emit: Instant,
+    last_bytes: u64,
+}
+
+impl ProgressTracker {
+    // offset 为续传时已有的字节数，不计入速度
+    fn new(task_id: &str, kind: StreamKind, total: Option<u64>, offset: u64) -> Self {
+        Self {
+            task_id: task_id.to_string(),
+            kind,
+            total,
+            downloaded: offset,
+            speed: 0.0,
+            last_emit: Instant::now(),
+            last_bytes: offset,
         }
+    }
+
+    // 记录新写入的字节数，到达上报间隔时返回一次进度快照
+    fn advance(&mut self, bytes: u64) -> Option<DownloadProgress> {
+        self.downloaded += bytes;
+
+        let elapsed = self.last_emit.elapsed();
+        if elapsed < PROGRESS_INTERVAL {
+            return None;
+        }
+
+        let instant_speed = (self.downloaded - self.last_bytes) as f64 / elapsed.as_secs_f64();
+        // 指数平滑，避免速度数字来回跳动
+        self.speed = if self.speed == 0.0 { instant_speed } else { self.speed * 0.7 + instant_speed * 0.3 };
+        self.last_emit = Instant::now();
+        self.last_bytes = self.downloaded;
+
+        Some(self.snapshot(format!("正在下载{}", self.kind.label())))
+    }
+
+    fn snapshot(&self, message: String) -> DownloadProgress {
+        let progress = match self.total {
+            Some(total) if total > 0 => (self.downloaded as f64 / total as f64 * 100.0).min(100.0),
+            _ => 0.0,
+        };
+        let eta = match self.total {
+            Some(total) if self.speed > 0.0 => Some((total.saturating_sub(self.downloaded) as f64 / self.speed).ceil() as u64),
+            _ => None,
+        };
+
+        DownloadProgress {
+            task_id: self.task_id.clone(),
+            stream: self.kind,
+            downloaded: self.downloaded,
+            total: self.total,
+            speed: self.speed,
+            eta,
+            progress,
+            overall: progress,
+            message,
+        }
+    }
+}
+
+// 一次下载任务内各个流共享的上下文
+struct DownloadContext {
+    api: Arc<dyn BilibiliApi>,
+    media: reqwest::Client,
+    bvid: String,
+    cid: Option<u64>,
+    task_id: String,
+    options: DownloadOptions,
+}
+
+// 分段下载的一个字节区间（闭区间）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+struct SegmentRange {
+    start: u64,
+    end: u64,
+}
+
+impl SegmentRange {
+    fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+// .part 文件旁的续传记录
+#[derive(Debug, Serialize, Deserialize)]
+struct PartMeta {
+    url: String,
+    bvid: String,
+    quality: u32,
+    #[serde(default)]
+    codecid: Option<u32>,
+    total: Option<u64>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    // 分段下载尚未拼接时记录分段方案，单连接下载或拼接完成后为空
+    #[serde(default)]
+    segments: Vec<SegmentRange>,
+}
+
+// 返回 (.part 文件, 续传记录) 路径
+fn part_paths(path: &Path) -> (PathBuf, PathBuf) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (
+        path.with_file_name(format!("{}.part", name)),
+        path.with_file_name(format!("{}.part.json", name)),
+    )
+}
+
+fn load_part_meta(meta_path: &Path) -> Option<PartMeta> {
+    let content = fs::read_to_string(meta_path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_part_meta(meta_path: &Path, meta: &PartMeta) -> Result<(), DilidiliError> {
+    let content = serde_json::to_string(meta)?;
+    fs::write(meta_path, content).map_err(|e| DilidiliError::Filesystem(format!("写入续传记录失败: {}", e)))
+}
+
+// 下载完成后将 .part 重命名为正式文件并删除续传记录
+fn finish_part(path: &Path) -> Result<(), DilidiliError> {
+    let (part_path, meta_path) = part_paths(path);
+    fs::rename(&part_path, path).map_err(|e| DilidiliError::Filesystem(format!("重命名下载文件失败: {}", e)))?;
+    let _ = fs::remove_file(&meta_path);
+    Ok(())
+}
+
+// B站 CDN 签名链接带有 deadline 参数（Unix 秒），提前一分钟视为过期
+fn url_expired(url: &str) -> bool {
+    let deadline = url::Url::parse(url).ok().and_then(|u| {
+        u.query_pairs()
+            .find(|(key, _)| key == "deadline")
+            .and_then(|(_, value)| value.parse::<u64>().ok())
+    });
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    matches!(deadline, Some(deadline) if now + 60 >= deadline)
+}
+
+// 从 "bytes 100-999/1000" 中取出总大小
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    response.headers()
+        .get("content-range")?
+        .to_str()
+        .ok()?
+        .rsplit('/')
+        .next()?
+        .parse()
+        .ok()
+}
+
+fn header_string(response: &reqwest::Response, name: &str) -> Option<String> {
+    response.headers().get(name)?.to_str().ok().map(|v| v.to_string())
+}
+
+// 从 URL 中取出主机名，用于日志
+fn host_of(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_else(|| url.to_string())
+}
+
+// 这些状态码通常只是当前 CDN 节点的问题，换镜像可能恢复
+fn is_mirror_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 403 | 404 | 410 | 429) || status.is_server_error()
+}
+
+// 主地址在前，backup_url 镜像在后
+fn stream_urls(url: &str, backup_urls: &[String]) -> Vec<String> {
+    let mut urls = vec![url.to_string()];
+    urls.extend(backup_urls.iter().filter(|u| u.as_str() != url).cloned());
+    urls
+}
+
+// 一个流的标识：同一画质下不同编码是不同的流
+#[derive(Debug, Clone, Copy)]
+struct StreamKey {
+    kind: StreamKind,
+    quality: u32,
+    codecid: Option<u32>,
+    segment: Option<usize>, // durl 分段序号
+}
+
+impl StreamKey {
+    fn matches(&self, quality: u32, codecid: Option<u32>) -> bool {
+        self.quality == quality && (self.codecid.is_none() || self.codecid == codecid)
+    }
+}
+
+// 签名链接过期后重新获取同一画质、同一编码的流地址（含镜像）
+async fn refresh_stream_urls(ctx: &DownloadContext, key: StreamKey) -> Result<Vec<String>, DilidiliError> {
+    let cid = ctx.cid.ok_or_else(|| DilidiliError::InvalidInput("缺少 cid，无法刷新流地址".to_string()))?;
+    println!("🔄 重新获取{}地址 (quality={}, codecid={:?})", key.kind.label(), key.quality, key.codecid);
+
+    let streams = crate::video::fetch_video_streams(ctx.api.as_ref(), &ctx.bvid, cid).await?;
+    let urls = match key.kind {
+        StreamKind::Video => streams.video_streams.into_iter()
+            .find(|s| key.matches(s.quality, s.codecid))
+            .and_then(|s| match key.segment {
+                Some(index) => s.segments.get(index).map(|segment| stream_urls(&segment.url, &segment.backup_urls)),
+                None => s.url.map(|url| stream_urls(&url, &s.backup_urls)),
+            }),
+        StreamKind::Audio => streams.audio_streams.into_iter()
+            .find(|s| key.matches(s.quality, s.codecid))
+            .and_then(|s| s.url.map(|url| stream_urls(&url, &s.backup_urls))),
+    };
+    urls.ok_or_else(|| DilidiliError::InvalidInput(format!("刷新后未找到对应的{} (quality={})", key.kind.label(), key.quality)))
+}
+
+// 一个流的候选地址，当前地址失败时依次切换到镜像，全部失败后重新获取一次地址
+struct Mirrors {
+    urls: Vec<String>,
+    index: usize,
+    refreshed: bool,
+}
+
+impl Mirrors {
+    fn new(urls: Vec<String>) -> Self {
+        Self { urls, index: 0, refreshed: false }
+    }
+
+    fn current(&self) -> &str {
+        &self.urls[self.index]
+    }
+
+    // 当前地址排在最前的完整列表
+    fn ordered(&self) -> Vec<String> {
+        let mut urls = self.urls[self.index..].to_vec();
+        urls.extend_from_slice(&self.urls[..self.index]);
+        urls
+    }
+
+    async fn refresh(&mut self, ctx: &DownloadContext, key: StreamKey) -> Result<(), DilidiliError> {
+        self.urls = refresh_stream_urls(ctx, key).await?;
+        self.index = 0;
+        self.refreshed = true;
+        Ok(())
+    }
+
+    // 签名已过期时先换新地址，省去一次必然失败的请求
+    async fn ensure_fresh(&mut self, ctx: &DownloadContext, key: StreamKey) -> Result<(), DilidiliError> {
+        if !self.refreshed && ctx.cid.is_some() && url_expired(self.current()) {
+            self.refresh(ctx, key).await?;
+        }
+        Ok(())
+    }
+
+    async fn next(&mut self, ctx: &DownloadContext, key: StreamKey, reason: &str) -> Result<(), DilidiliError> {
+        println!("⚠️ {} {} 失败: {}", key.kind.label(), host_of(self.current()), reason);
+
+        if self.index + 1 < self.urls.len() {
+            self.index += 1;
+            println!("🔀 {}切换到镜像: {}", key.kind.label(), host_of(self.current()));
+            return Ok(());
+        }
+        if self.refreshed || ctx.cid.is_none() {
+            return Err(DilidiliError::Network(format!("{}所有地址均不可用: {}", key.kind.label(), reason)));
+        }
+        self.refresh(ctx, key).await
+    }
+}
+
+// 一次请求失败的原因
+enum AttemptError {
+    // 网络错误、超时、卡住或 CDN 节点拒绝，换镜像重试
+    Mirror(String),
+    // 续传范围无效，同一地址从头下载
+    Restart,
+    Fatal(DilidiliError),
+}
+
+// 读取下一块数据，超过 STALL_TIMEOUT 没有数据视为卡住
+async fn next_chunk(response: &mut reqwest::Response) -> Result<Option<bytes::Bytes>, AttemptError> {
+    match tokio::time::timeout(STALL_TIMEOUT, response.chunk()).await {
+        Ok(Ok(chunk)) => Ok(chunk),
+        Ok(Err(e)) => Err(AttemptError::Mirror(format!("读取数据失败: {}", e))),
+        Err(_) => Err(AttemptError::Mirror(format!("{} 秒没有收到数据", STALL_TIMEOUT.as_secs()))),
+    }
+}
+
+// 用一个地址下载（或续传）到 .part 文件
+async fn stream_attempt<F: Fn(DownloadProgress)>(
+    ctx: &DownloadContext,
+    key: StreamKey,
+    url: &str,
+    part_path: &Path,
+    meta_path: &Path,
+    on_progress: &F,
+) -> Result<u64, AttemptError> {
+    // 只有同一视频、同一画质的 .part 才能续传
+    let meta = load_part_meta(meta_path)
+        .filter(|m| m.bvid == ctx.bvid && m.quality == key.quality && m.codecid == key.codecid && m.segments.is_empty());
+    let mut offset = match &meta {
+        Some(_) => fs::metadata(part_path).map(|m| m.len()).unwrap_or(0),
+        None => 0,
+    };
+
+    if let Some(total) = meta.as_ref().and_then(|m| m.total) {
+        if offset == total {
+            println!("✅ {}上次已下载完成，跳过", key.kind.label());
+            let tracker = ProgressTracker::new(&ctx.task_id, key.kind, Some(total), total);
+            on_progress(tracker.snapshot(format!("{}下载完成", key.kind.label())));
+            return Ok(total);
+        }
+        if offset > total {
+            offset = 0;
+        }
+    }
+
+    println!("URL: {}", url);
+    let mut request = media_request(&ctx.media, url, &ctx.bvid);
+    if offset > 0 {
+        println!("从 {} bytes 处续传{}", offset, key.kind.label());
+        request = request.header("Range", format!("bytes={}-", offset));
+        // 内容有变化时服务器会返回 200 完整内容
+        if let Some(validator) = meta.as_ref().and_then(|m| m.etag.clone().or_else(|| m.last_modified.clone())) {
+            request = request.header("If-Range", validator);
+        }
+    }
+
+    let mut response = request
+        .send()
+        .await
+        .map_err(|e| AttemptError::Mirror(format!("请求失败: {}", e)))?;
+
+    let status = response.status();
+    println!("{}响应状态: {}", key.kind.label(), status);
+
+    if is_mirror_status(status) {
+        return Err(AttemptError::Mirror(format!("HTTP {}", status)));
+    }
+
+    if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        println!("⚠️ 续传范围无效，重新下载{}", key.kind.label());
+        let _ = fs::remove_file(part_path);
+        let _ = fs::remove_file(meta_path);
+        return Err(AttemptError::Restart);
+    }
+
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_else(|_| "无法读取错误信息".to_string());
+        return Err(AttemptError::Fatal(DilidiliError::Http {
+            status: status.as_u16(),
+            message: format!("{}下载失败: {}", key.kind.label(), error_text),
+        }));
+    }
+
+    // 206 在原文件后追加；200 说明服务器忽略了 Range 或内容已变化，从头下载
+    let resuming = offset > 0 && status == reqwest::StatusCode::PARTIAL_CONTENT;
+    if !resuming {
+        offset = 0;
+    }
+    let total = if resuming {
+        content_range_total(&response).or_else(|| meta.as_ref().and_then(|m| m.total))
+    } else {
+        response.content_length()
+    };
+
+    save_part_meta(meta_path, &PartMeta {
+        url: url.to_string(),
+        bvid: ctx.bvid.clone(),
+        quality: key.quality,
+        codecid: key.codecid,
+        total,
+        etag: header_string(&response, "etag"),
+        last_modified: header_string(&response, "last-modified"),
+        segments: Vec::new(),
+    })
+    .map_err(AttemptError::Fatal)?;
+
+    let mut file = if resuming {
+        tokio::fs::OpenOptions::new().append(true).open(part_path).await
     } else {
-        return Err("视频流URL为空".to_string());
+        tokio::fs::File::create(part_path).await
     }
+    .map_err(|e| AttemptError::Fatal(DilidiliError::Filesystem(format!("创建文件失败: {}", e))))?;
 
-    // 下载音频流
-    if let Some(audio_url) = &audio_stream.url {
-        println!("=== 开始下载音频流 ===");
-        println!("音频URL: {}", audio_url);
-        println!("保存到: {:?}", audio_path);
-        
-        let response = client.get(audio_url)
-            .header("Cookie", &cookies)
-            .header("Referer", &format!("https://www.bilibili.com/video/{}", video_data.bvid))
-            .header("Origin", "https://www.bilibili.com")
-            .header("Accept", "*/*")
-            .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
-            .header("Accept-Encoding", "gzip, deflate, br")
-            .header("Connection", "keep-alive")
-            .header("Sec-Fetch-Dest", "empty")
-            .header("Sec-Fetch-Mode", "cors")
-            .header("Sec-Fetch-Site", "cross-site")
+    let mut tracker = ProgressTracker::new(&ctx.task_id, key.kind, total, offset);
+    on_progress(tracker.snapshot(format!("开始下载{}", key.kind.label())));
+
+    let result = async {
+        while let Some(chunk) = next_chunk(&mut response).await? {
+            file.write_all(&chunk).await.map_err(|e| AttemptError::Fatal(DilidiliError::Filesystem(format!("写入文件失败: {}", e))))?;
+            if let Some(progress) = tracker.advance(chunk.len() as u64) {
+                on_progress(progress);
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    // 出错时也要等已提交的写入落盘，否则下次续传取到的文件长度不准确
+    file.flush().await.map_err(|e| AttemptError::Fatal(DilidiliError::Filesystem(format!("写入文件失败: {}", e))))?;
+    result?;
+
+    if let Some(total) = tracker.total {
+        if tracker.downloaded != total {
+            return Err(AttemptError::Mirror(format!("数据不完整: {}/{} bytes", tracker.downloaded, total)));
+        }
+    }
+
+    on_progress(tracker.snapshot(format!("{}下载完成", key.kind.label())));
+    Ok(tracker.downloaded)
+}
+
+// 将一个 DASH 流边下载边写入 .part 文件，支持断点续传和镜像切换，返回文件总字节数
+async fn download_stream<F: Fn(DownloadProgress)>(
+    ctx: &DownloadContext,
+    key: StreamKey,
+    urls: Vec<String>,
+    path: &Path,
+    on_progress: &F,
+) -> Result<u64, DilidiliError> {
+    let (part_path, meta_path) = part_paths(path);
+
+    println!("=== 开始下载{} ===", key.kind.label());
+    println!("保存到: {:?}", part_path);
+
+    let mut mirrors = Mirrors::new(urls);
+    mirrors.ensure_fresh(ctx, key).await?;
+
+    loop {
+        println!("{}使用 CDN: {}", key.kind.label(), host_of(mirrors.current()));
+        match stream_attempt(ctx, key, mirrors.current(), &part_path, &meta_path, on_progress).await {
+            Ok(size) => {
+                println!("✅ {}下载完成: {} bytes (来自 {})", key.kind.label(), size, host_of(mirrors.current()));
+                return Ok(size);
+            }
+            Err(AttemptError::Restart) => continue,
+            Err(AttemptError::Mirror(reason)) => mirrors.next(ctx, key, &reason).await?,
+            Err(AttemptError::Fatal(e)) => return Err(e),
+        }
+    }
+}
+
+// 按连接数和最小分段大小切分字节区间
+fn plan_segments(total: u64, connections: usize, min_chunk_size: u64) -> Vec<SegmentRange> {
+    let max_by_size = (total / min_chunk_size.max(1)).max(1);
+    let count = (connections.max(1) as u64).min(max_by_size);
+    let chunk = total.div_ceil(count);
+
+    (0..count)
+        .map(|i| SegmentRange { start: i * chunk, end: ((i + 1) * chunk).min(total) - 1 })
+        .filter(|range| range.start < total)
+        .collect()
+}
+
+// 第 index 个分段的临时文件：xxx.part.0、xxx.part.1 ...
+fn segment_path(part_path: &Path, index: usize) -> PathBuf {
+    let name = part_path.file_name().unwrap_or_default().to_string_lossy();
+    part_path.with_file_name(format!("{}.{}", name, index))
+}
+
+fn remove_segments(part_path: &Path, count: usize) {
+    for index in 0..count {
+        let _ = fs::remove_file(segment_path(part_path, index));
+    }
+}
+
+// 探测结果：总大小和校验头
+struct StreamProbe {
+    total: u64,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+// 用 Range: bytes=0-0 探测总大小，服务器不支持 Range 时返回 None
+async fn probe_stream(ctx: &DownloadContext, key: StreamKey, mirrors: &mut Mirrors) -> Result<Option<StreamProbe>, DilidiliError> {
+    loop {
+        let response = match media_request(&ctx.media, mirrors.current(), &ctx.bvid)
+            .header("Range", "bytes=0-0")
             .send()
             .await
-            .map_err(|e| format!("音频流请求失败: {}", e))?;
-
-        println!("音频流响应状态: {}", response.status());
-        println!("音频流响应头: {:?}", response.headers());
+        {
+            Ok(response) => response,
+            Err(e) => {
+                mirrors.next(ctx, key, &format!("请求失败: {}", e)).await?;
+                continue;
+            }
+        };
 
         let status = response.status();
-        if status.is_success() {
-            let content = response.bytes().await.map_err(|e| format!("读取音频流内容失败: {}", e))?;
-            println!("音频流大小: {} bytes", content.len());
-            
-            let mut file = std::fs::File::create(&audio_path).map_err(|e| format!("创建音频文件失败: {}", e))?;
-            file.write_all(&content).map_err(|e| format!("写入音频文件失败: {}", e))?;
-            println!("✅ 音频流下载完成");
-        } else {
-            let error_text = response.text().await.unwrap_or_else(|_| "无法读取错误信息".to_string());
-            return Err(format!("音频流下载失败: {} - {}", status, error_text));
+        if is_mirror_status(status) {
+            mirrors.next(ctx, key, &format!("HTTP {}", status)).await?;
+            continue;
         }
-    } else {
-        println!("⚠️ 音频流URL为空，跳过音频下载");
+
+        if status != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Ok(None);
+        }
+
+        return Ok(content_range_total(&response).map(|total| StreamProbe {
+            total,
+            etag: header_string(&response, "etag"),
+            last_modified: header_string(&response, "last-modified"),
+        }));
     }
+}
 
-    println!("=== 处理下载完成的文件 ===");
-    
-    // 检查文件是否存在
-    let video_exists = video_path.exists();
-    let audio_exists = audio_path.exists();
-    
-    println!("视频文件存在: {}, 音频文件存在: {}", video_exists, audio_exists);
+// 下载单个分段的剩余部分，已写入的字节直接计入进度
+async fn fetch_segment<F: Fn(DownloadProgress)>(
+    ctx: &DownloadContext,
+    url: &str,
+    seg_path: &Path,
+    range: SegmentRange,
+    tracker: &Mutex<ProgressTracker>,
+    on_progress: &F,
+) -> Result<(), DilidiliError> {
+    let existing = fs::metadata(seg_path).map(|m| m.len()).unwrap_or(0);
+    if existing == range.len() {
+        return Ok(());
+    }
 
-    // 如果只有视频流，直接重命名
-    if video_exists && !audio_exists {
-        println!("只有视频流，重命名为最终文件");
-        fs::rename(&video_path, &final_path).map_err(|e| format!("重命名视频文件失败: {}", e))?;
-        return Ok(format!("视频下载完成: {:?}", final_path));
+    let mut existing = existing;
+    if existing > range.len() {
+        let _ = fs::remove_file(seg_path);
+        existing = 0;
     }
 
-    // 如果只有音频流，直接重命名
-    if !video_exists && audio_exists {
-        println!("只有音频流，重命名为最终文件");
-        let audio_final_path = downloads_dir.join(format!("{}.mp3", safe_title));
-        fs::rename(&audio_path, &audio_final_path).map_err(|e| format!("重命名音频文件失败: {}", e))?;
-        return Ok(format!("音频下载完成: {:?}", audio_final_path));
+    let mut response = media_request(&ctx.media, url, &ctx.bvid)
+        .header("Range", format!("bytes={}-{}", range.start + existing, range.end))
+        .send()
+        .await
+        .map_err(|e| DilidiliError::Network(format!("分段请求失败: {}", e)))?;
+
+    let status = response.status();
+    if status != reqwest::StatusCode::PARTIAL_CONTENT {
+        return Err(DilidiliError::Http { status: status.as_u16(), message: "分段请求失败".to_string() });
     }
 
-    // 如果都有，进行FFmpeg合并
-    if video_exists && audio_exists {
-        println!("视频和音频都存在，开始FFmpeg合并");
-        
-        // 调用FFmpeg合并
-        match crate::ffmpeg::merge_video_audio(
-            app_handle.clone(),
-            video_path.to_string_lossy().to_string(),
-            audio_path.to_string_lossy().to_string(),
-            final_path.to_string_lossy().to_string(),
-        ).await {
-            Ok(_) => {
-                println!("✅ FFmpeg合并成功");
-                return Ok(format!("视频下载并合并完成: {:?}", final_path));
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(seg_path)
+        .await
+        .map_err(|e| DilidiliError::Filesystem(format!("创建分段文件失败: {}", e)))?;
+
+    let mut written = existing;
+    let result = async {
+        while let Some(chunk) = next_chunk(&mut response).await.map_err(|e| match e {
+            AttemptError::Mirror(reason) => DilidiliError::Network(reason),
+            AttemptError::Fatal(e) => e,
+            AttemptError::Restart => DilidiliError::Network("分段需要重新下载".to_string()),
+        })? {
+            if written + chunk.len() as u64 > range.len() {
+                return Err(DilidiliError::Network("分段内容超出请求范围".to_string()));
+            }
+            file.write_all(&chunk).await.map_err(|e| DilidiliError::Filesystem(format!("写入分段文件失败: {}", e)))?;
+            written += chunk.len() as u64;
+
+            let progress = tracker.lock().unwrap().advance(chunk.len() as u64);
+            if let Some(progress) = progress {
+                on_progress(progress);
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    // 出错时也要等已提交的写入落盘，重试时按文件长度续传
+    file.flush().await.map_err(|e| DilidiliError::Filesystem(format!("写入分段文件失败: {}", e)))?;
+    result?;
+
+    if written != range.len() {
+        return Err(DilidiliError::Network(format!("分段不完整: {}/{} bytes", written, range.len())));
+    }
+    Ok(())
+}
+
+// 分段失败时单独重试，每次重试轮换到下一个镜像，不影响其他分段
+#[allow(clippy::too_many_arguments)]
+async fn download_segment<F: Fn(DownloadProgress)>(
+    ctx: &DownloadContext,
+    key: StreamKey,
+    urls: &[String],
+    index: usize,
+    seg_path: &Path,
+    range: SegmentRange,
+    tracker: &Mutex<ProgressTracker>,
+    on_progress: &F,
+) -> Result<(), DilidiliError> {
+    let mut attempt = 0;
+    loop {
+        let url = &urls[attempt as usize % urls.len()];
+        match fetch_segment(ctx, url, seg_path, range, tracker, on_progress).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < ctx.options.segment_retries => {
+                attempt += 1;
+                println!("⚠️ {}分段 {} 在 {} 失败: {}，第 {} 次重试", key.kind.label(), index, host_of(url), e, attempt);
+                tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
             }
             Err(e) => {
-                println!("⚠️ FFmpeg合并失败: {}，使用视频流", e);
-                // 合并失败，保留视频流
-                let _ = fs::remove_file(&audio_path);
-                fs::rename(&video_path, &final_path).map_err(|e| format!("重命名合成文件失败: {}", e))?;
-                return Ok(format!("视频下载完成（合并失败，仅视频）: {:?}", final_path));
+                println!("❌ {}分段 {} 下载失败: {}", key.kind.label(), index, e);
+                return Err(e);
             }
         }
     }
+}
+
+// 按顺序把分段拼接成完整的 .part 文件
+async fn join_segments(part_path: &Path, count: usize) -> Result<(), DilidiliError> {
+    let mut output = tokio::fs::File::create(part_path)
+        .await
+        .map_err(|e| DilidiliError::Filesystem(format!("创建文件失败: {}", e)))?;
+
+    for index in 0..count {
+        let mut segment = tokio::fs::File::open(segment_path(part_path, index))
+            .await
+            .map_err(|e| DilidiliError::Filesystem(format!("打开分段 {} 失败: {}", index, e)))?;
+        tokio::io::copy(&mut segment, &mut output)
+            .await
+            .map_err(|e| DilidiliError::Filesystem(format!("拼接分段 {} 失败: {}", index, e)))?;
+    }
+
+    output.flush().await.map_err(|e| DilidiliError::Filesystem(format!("写入文件失败: {}", e)))?;
+    remove_segments(part_path, count);
+    Ok(())
+}
+
+// 多连接分段下载一个流，服务器不支持 Range 或文件太小时退回单连接
+async fn download_stream_segmented<F: Fn(DownloadProgress)>(
+    ctx: &DownloadContext,
+    key: StreamKey,
+    urls: Vec<String>,
+    path: &Path,
+    on_progress: &F,
+) -> Result<u64, DilidiliError> {
+    let (part_path, meta_path) = part_paths(path);
+
+    let mut mirrors = Mirrors::new(urls);
+    mirrors.ensure_fresh(ctx, key).await?;
+
+    let Some(probe) = probe_stream(ctx, key, &mut mirrors).await? else {
+        println!("⚠️ {}不支持分段下载，改用单连接", key.kind.label());
+        return download_stream(ctx, key, mirrors.ordered(), path, on_progress).await;
+    };
+
+    let previous = load_part_meta(&meta_path);
+    let meta = previous.as_ref().filter(|m| {
+        m.bvid == ctx.bvid && m.quality == key.quality && m.codecid == key.codecid && m.total == Some(probe.total) && m.etag == probe.etag
+    });
+
+    // 上次已经拼接完成
+    if meta.is_some_and(|m| m.segments.is_empty())
+        && fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0) == probe.total
+    {
+        println!("✅ {}上次已下载完成，跳过", key.kind.label());
+        let tracker = ProgressTracker::new(&ctx.task_id, key.kind, Some(probe.total), probe.total);
+        on_progress(tracker.snapshot(format!("{}下载完成", key.kind.label())));
+        return Ok(probe.total);
+    }
+
+    let segments = match meta {
+        Some(m) if !m.segments.is_empty() => m.segments.clone(),
+        _ => {
+            if let Some(previous) = &previous {
+                remove_segments(&part_path, previous.segments.len());
+            }
+            plan_segments(probe.total, ctx.options.connections, ctx.options.min_chunk_size)
+        }
+    };
+
+    if segments.len() <= 1 {
+        return download_stream(ctx, key, mirrors.ordered(), path, on_progress).await;
+    }
+
+    println!("=== 分段下载{}: {} bytes, {} 个分段, CDN: {} ===", key.kind.label(), probe.total, segments.len(), host_of(mirrors.current()));
+
+    let urls = mirrors.ordered();
+    let mut meta = PartMeta {
+        url: urls[0].clone(),
+        bvid: ctx.bvid.clone(),
+        quality: key.quality,
+        codecid: key.codecid,
+        total: Some(probe.total),
+        etag: probe.etag,
+        last_modified: probe.last_modified,
+        segments,
+    };
+    save_part_meta(&meta_path, &meta)?;
+
+    let already: u64 = meta.segments.iter().enumerate()
+        .map(|(index, range)| fs::metadata(segment_path(&part_path, index)).map(|m| m.len()).unwrap_or(0).min(range.len()))
+        .sum();
+    let tracker = Mutex::new(ProgressTracker::new(&ctx.task_id, key.kind, Some(probe.total), already));
+    on_progress(tracker.lock().unwrap().snapshot(format!("开始下载{}", key.kind.label())));
+
+    let tasks = meta.segments.iter().enumerate().map(|(index, range)| {
+        let seg_path = segment_path(&part_path, index);
+        let tracker = &tracker;
+        let urls = &urls;
+        async move { download_segment(ctx, key, urls, index, &seg_path, *range, tracker, on_progress).await }
+    });
+    futures_util::future::try_join_all(tasks).await?;
+
+    join_segments(&part_path, meta.segments.len()).await?;
+    meta.segments.clear();
+    save_part_meta(&meta_path, &meta)?;
+
+    let tracker = tracker.into_inner().unwrap();
+    on_progress(tracker.snapshot(format!("{}下载完成", key.kind.label())));
+    println!("✅ {}分段下载完成: {} bytes", key.kind.label(), probe.total);
+    Ok(probe.total)
+}
+
+// 按下载选项选择单连接或分段下载
+async fn fetch_stream<F: Fn(DownloadProgress)>(
+    ctx: &DownloadContext,
+    key: StreamKey,
+    urls: Vec<String>,
+    path: &Path,
+    on_progress: &F,
+) -> Result<u64, DilidiliError> {
+    if ctx.options.segmented {
+        download_stream_segmented(ctx, key, urls, path, on_progress).await
+    } else {
+        download_stream(ctx, key, urls, path, on_progress).await
+    }
+}
+
+// 一次下载涉及的本地路径
+struct DownloadPaths {
+    dir: PathBuf,      // 输出文件所在目录（含模板中的子目录）
+    temp_stem: String, // 临时文件名前缀，按 BV 号、分P和所选流区分，续传时保持不变
+    video: PathBuf,
+    audio: PathBuf,
+    output: PathBuf,
+}
+
+impl DownloadPaths {
+    // durl 第 index 段的临时文件
+    fn durl_segment(&self, index: usize, format: &str) -> PathBuf {
+        self.dir.join(format!("{}_part{}.{}", self.temp_stem, index + 1, format))
+    }
+}
+
+// 下载流水线依赖的外部环境：命令中由 AppHandle 构造，集成测试中直接构造
+pub struct Downloader {
+    pub api: Arc<dyn BilibiliApi>,
+    pub output_dir: PathBuf,       // 输出根目录，模板中的子目录在其下创建
+    pub filename_template: String, // 单P视频的文件名模板
+    pub page_template: String,     // 多P视频的文件名模板
+    pub ffmpeg: Option<PathBuf>,   // 找不到 ffmpeg 时为空
+}
+
+impl Downloader {
+    pub fn from_app(app_handle: &tauri::AppHandle, profile_id: Option<&str>) -> Result<Self, DilidiliError> {
+        let settings = crate::settings::load_settings(app_handle)?;
+
+        Ok(Self {
+            api: Arc::new(crate::profiles::client_for_profile(app_handle, profile_id)?),
+            output_dir: output_dir(app_handle, &settings)?,
+            filename_template: settings.filename_template,
+            page_template: settings.page_template,
+            ffmpeg: crate::ffmpeg::get_ffmpeg_path(app_handle).ok(),
+        })
+    }
+
+    fn ffmpeg(&self) -> Result<&Path, DilidiliError> {
+        self.ffmpeg.as_deref().ok_or(DilidiliError::FfmpegNotFound)
+    }
+}
+
+// 设置中的输出根目录，未设置时使用 <下载目录>/DILIDILI
+fn output_dir(app_handle: &tauri::AppHandle, settings: &AppSettings) -> Result<PathBuf, DilidiliError> {
+    match settings.output_dir.as_deref().map(str::trim).filter(|dir| !dir.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(app_handle.path().download_dir()?.join("DILIDILI")),
+    }
+}
+
+fn download_paths(
+    output_dir: &Path,
+    filename_template: &str,
+    page_template: &str,
+    request: &DownloadRequest,
+) -> Result<DownloadPaths, DilidiliError> {
+    // 按模板生成输出路径，多P视频使用分P模板
+    let template = if request.video_data.pages.len() > 1 {
+        page_template
+    } else {
+        filename_template
+    };
+    let output_stem = output_dir.join(crate::naming::render_template(template, request));
+    let file_name = output_stem.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let dir = output_stem.parent().map(Path::to_path_buf).unwrap_or_else(|| output_dir.to_path_buf());
+
+    // 临时文件名带上所选流的清晰度和编码，同一分P的不同画质互不覆盖
+    let (video_stream, audio_stream) = request.selected_streams();
+    let mut temp_stem = format!(
+        "{}_P{}",
+        crate::naming::sanitize_component(&request.video_data.bvid),
+        request.page().map(|page| page.page).unwrap_or(1)
+    );
+    if let Some(video) = video_stream {
+        temp_stem.push_str(&format!("_v{}c{}", video.quality, video.codecid.unwrap_or(0)));
+    }
+    if let Some(audio) = audio_stream {
+        temp_stem.push_str(&format!("_a{}", audio.quality));
+    }
+
+    // 仅音频时扩展名跟随音频编码；合并时无损音轨封装进 MKV，AAC 和杜比音轨使用 MP4
+    let container = match request.selected_streams() {
+        (None, Some(audio)) => audio.codec.extension(),
+        (Some(_), Some(audio)) if audio.codec == AudioCodec::Flac => "mkv",
+        _ => "mp4",
+    };
+
+    Ok(DownloadPaths {
+        video: dir.join(format!("{}_video.m4s", temp_stem)),
+        audio: dir.join(format!("{}_audio.m4s", temp_stem)),
+        output: dir.join(format!("{}.{}", file_name, container)),
+        dir,
+        temp_stem,
+    })
+}
+
+// 任务会写入的路径：临时文件前缀和输出文件，队列据此避免同时运行写同一组文件的任务
+pub fn claimed_paths(
+    app_handle: &tauri::AppHandle,
+    settings: &AppSettings,
+    request: &DownloadRequest,
+) -> Result<[PathBuf; 2], DilidiliError> {
+    let paths = download_paths(&output_dir(app_handle, settings)?, &settings.filename_template, &settings.page_template, request)?;
+    Ok([paths.dir.join(&paths.temp_stem), paths.output])
+}
+
+// 删除未完成下载留下的 .part、分段、续传记录，以及合并失败时保留的音视频流
+pub fn remove_partial_files(app_handle: &tauri::AppHandle, request: &DownloadRequest) -> Result<(), DilidiliError> {
+    let settings = crate::settings::load_settings(app_handle)?;
+    let paths = download_paths(&output_dir(app_handle, &settings)?, &settings.filename_template, &settings.page_template, request)?;
+
+    let mut files = vec![paths.video.clone(), paths.audio.clone()];
+    if let Some(video_stream) = &request.video_stream {
+        files.extend((0..video_stream.segments.len()).map(|i| paths.durl_segment(i, &video_stream.format)));
+    }
 
-    // 如果都不存在
-    Err("下载失败：没有成功下载任何文件".to_string())
-} 
\ No newline at end of file
+    for path in &files {
+        let (part_path, meta_path) = part_paths(path);
+        if let Some(meta) = load_part_meta(&meta_path) {
+            remove_segments(&part_path, meta.segments.len());
+        }
+        let _ = fs::remove_file(&part_path);
+        let _ = fs::remove_file(&meta_path);
+        let _ = fs::remove_file(path);
+    }
+    Ok(())
+}
+
+// 下载 durl 的全部分段并用ffmpeg拼接成一个文件
+async fn download_durl<F: Fn(DownloadProgress)>(
+    downloader: &Downloader,
+    ctx: &DownloadContext,
+    video_stream: &VideoStream,
+    paths: &DownloadPaths,
+    on_progress: &F,
+) -> Result<String, DilidiliError> {
+    let count = video_stream.segments.len();
+    println!("=== 下载 durl 分段: {} 段 ===", count);
+
+    let mut files = Vec::with_capacity(count);
+    for (index, segment) in video_stream.segments.iter().enumerate() {
+        let path = paths.durl_segment(index, &video_stream.format);
+        let key = StreamKey { kind: StreamKind::Video, quality: video_stream.quality, codecid: video_stream.codecid, segment: Some(index) };
+
+        // 把单段进度换算成整体进度
+        let scaled = |mut progress: DownloadProgress| {
+            progress.progress = (index as f64 * 100.0 + progress.progress) / count as f64;
+            progress.message = format!("[{}/{}] {}", index + 1, count, progress.message);
+            on_progress(progress);
+        };
+        fetch_stream(ctx, key, stream_urls(&segment.url, &segment.backup_urls), &path, &scaled).await?;
+        files.push(path);
+    }
+
+    // 全部分段完成后再转正
+    for path in &files {
+        finish_part(path)?;
+    }
+
+    // 不覆盖已有的同名文件
+    let mut output = crate::naming::unique_path(&paths.output);
+    match (files.as_slice(), downloader.ffmpeg()) {
+        // MP4 单段已是最终格式，直接重命名
+        ([file], _) if video_stream.format == "mp4" => {
+            fs::rename(file, &output).map_err(|e| DilidiliError::Filesystem(format!("重命名视频文件失败: {}", e)))?;
+        }
+        // FLV 单段无损重新封装为 MP4
+        ([file], Ok(ffmpeg)) => {
+            crate::ffmpeg::remux_stream(ffmpeg, file, &output).await?;
+        }
+        // 没有 ffmpeg 时保留原格式，扩展名跟随流格式
+        ([file], Err(_)) => {
+            println!("⚠️ 未找到ffmpeg，保留 {} 格式", video_stream.format);
+            output = crate::naming::unique_path(&paths.output.with_extension(&video_stream.format));
+            fs::rename(file, &output).map_err(|e| DilidiliError::Filesystem(format!("重命名视频文件失败: {}", e)))?;
+        }
+        (_, ffmpeg) => {
+            crate::ffmpeg::concat_segments(ffmpeg?, &files, &output).await?;
+        }
+    }
+
+    println!("✅ durl 分段下载完成");
+    Ok(format!("视频下载完成: {:?}", output))
+}
+
+// 下载视频
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn download_video(
+    app_handle: tauri::AppHandle,
+    video_data: VideoData,
+    video_stream: Option<VideoStream>,
+    audio_stream: Option<AudioStream>,
+    cid: Option<u64>,
+    mode: Option<DownloadMode>,
+    task_id: Option<String>,
+    options: Option<DownloadOptions>
+) -> Result<String, DilidiliError> {
+    // 进度事件以 task_id 区分，前端未传时使用 BV 号
+    let task_id = task_id.unwrap_or_else(|| video_data.bvid.clone());
+    let request = DownloadRequest {
+        video_data,
+        video_stream,
+        audio_stream,
+        cid,
+        mode: mode.unwrap_or_default(),
+        options: options.unwrap_or_default(),
+        profile_id: None,
+    };
+
+    run_download(&app_handle, &request, &task_id, |progress| {
+        let _ = app_handle.emit(DOWNLOAD_PROGRESS_EVENT, progress);
+    }).await
+}
+
+// 执行一次下载任务，单次下载命令和下载队列共用
+pub async fn run_download<F: Fn(DownloadProgress)>(
+    app_handle: &tauri::AppHandle,
+    request: &DownloadRequest,
+    task_id: &str,
+    on_progress: F,
+) -> Result<String, DilidiliError> {
+    Downloader::from_app(app_handle, request.profile_id.as_deref())?.run(request, task_id, on_progress).await
+}
+
+impl Downloader {
+    // 下载所选的流，下载完成后合并或封装为最终文件，返回结果说明
+    pub async fn run<F: Fn(DownloadProgress)>(
+        &self,
+        request: &DownloadRequest,
+        task_id: &str,
+        on_progress: F,
+    ) -> Result<String, DilidiliError> {
+        let video_data = &request.video_data;
+        let (video_stream, audio_stream) = request.selected_streams();
+
+        println!("=== 开始下载视频 ===");
+        println!("视频标题: {}", video_data.title);
+        println!("下载模式: {:?}", request.mode);
+        if let Some(video_stream) = video_stream {
+            println!("视频质量: {}", video_stream.label);
+            println!("视频URL: {:?}", video_stream.url);
+        }
+        if let Some(audio_stream) = audio_stream {
+            println!("音频质量: {}", audio_stream.description);
+            println!("音频URL: {:?}", audio_stream.url);
+        }
+        match (request.mode, video_stream, audio_stream) {
+            (DownloadMode::AudioOnly, _, None) => return Err(DilidiliError::InvalidInput("仅下载音频需要选择音频流".to_string())),
+            (DownloadMode::AudioOnly, _, _) if request.video_stream.as_ref().is_some_and(|v| !v.segments.is_empty()) => {
+                return Err(DilidiliError::InvalidInput("该视频只有音视频一体的 durl 流，不支持仅下载音频".to_string()));
+            }
+            (DownloadMode::Both | DownloadMode::VideoOnly, None, _) => return Err(DilidiliError::InvalidInput("视频流为空".to_string())),
+            _ => {}
+        }
+
+        // 创建下载目录
+        let paths = download_paths(&self.output_dir, &self.filename_template, &self.page_template, request)?;
+        fs::create_dir_all(&paths.dir)?;
+
+        let ctx = DownloadContext {
+            api: self.api.clone(),
+            media: self.api.media(),
+            bvid: video_data.bvid.clone(),
+            // 用于在签名链接过期时重新获取流地址，默认取第一个分P
+            cid: request.cid.or_else(|| video_data.pages.first().map(|p| p.cid)),
+            task_id: task_id.to_string(),
+            options: request.options.clone(),
+        };
+
+        // 换算整体进度：同时下载音视频时视频流占 90%，音频流占 10%
+        let video_weight = match (video_stream, audio_stream) {
+            (Some(_), Some(_)) => 0.9,
+            (Some(_), None) => 1.0,
+            (None, _) => 0.0,
+        };
+        let on_progress = |mut progress: DownloadProgress| {
+            progress.overall = match progress.stream {
+                StreamKind::Video => progress.progress * video_weight,
+                StreamKind::Audio => video_weight * 100.0 + progress.progress * (1.0 - video_weight),
+            };
+            on_progress(progress);
+        };
+
+        // durl 流逐段下载后拼接，不走音视频合并
+        if let Some(video_stream) = video_stream.filter(|v| !v.segments.is_empty()) {
+            if request.mode == DownloadMode::VideoOnly {
+                println!("⚠️ durl 流音视频封装在一起，将下载完整文件");
+            }
+            return download_durl(self, &ctx, video_stream, &paths, &on_progress).await;
+        }
+
+        let DownloadPaths { video: video_path, audio: audio_path, output: final_path, .. } = paths;
+
+        // 上次已下载完成但合并失败的流直接复用，重试时只需重新合并
+        let video_done = video_stream.is_some() && video_path.exists();
+        let audio_done = audio_stream.is_some_and(|a| a.url.is_some()) && audio_path.exists();
+        if video_done || audio_done {
+            println!("复用已下载的流: 视频 {}, 音频 {}", video_done, audio_done);
+        }
+
+        // 下载视频流
+        if let Some(video_stream) = video_stream.filter(|_| !video_done) {
+            let video_url = video_stream.url.as_ref().ok_or_else(|| DilidiliError::InvalidInput("视频流URL为空".to_string()))?;
+            let key = StreamKey { kind: StreamKind::Video, quality: video_stream.quality, codecid: video_stream.codecid, segment: None };
+            fetch_stream(&ctx, key, stream_urls(video_url, &video_stream.backup_urls), &video_path, &on_progress).await?;
+        }
+
+        // 下载音频流
+        let audio = audio_stream.and_then(|a| a.url.as_ref().map(|url| (a, url)));
+        if let Some((audio_stream, audio_url)) = audio.filter(|_| !audio_done) {
+            let key = StreamKey { kind: StreamKind::Audio, quality: audio_stream.quality, codecid: audio_stream.codecid, segment: None };
+            fetch_stream(&ctx, key, stream_urls(audio_url, &audio_stream.backup_urls), &audio_path, &on_progress).await?;
+        } else if audio.is_none() && request.mode == DownloadMode::AudioOnly {
+            return Err(DilidiliError::InvalidInput("音频流URL为空".to_string()));
+        } else if audio.is_none() && request.mode == DownloadMode::Both {
+            println!("⚠️ 音频流URL为空，跳过音频下载");
+        }
+
+        // 所有流都下载完成后再转正，中途失败时 .part 保留给下次续传
+        if video_stream.is_some() && !video_done {
+            finish_part(&video_path)?;
+        }
+        if audio.is_some() && !audio_done {
+            finish_part(&audio_path)?;
+        }
+
+        println!("=== 处理下载完成的文件 ===");
+    
+        // 检查文件是否存在
+        let video_exists = video_stream.is_some() && video_path.exists();
+        let audio_exists = audio.is_some() && audio_path.exists();
+    
+        println!("视频文件存在: {}, 音频文件存在: {}", video_exists, audio_exists);
+
+        // 不覆盖已有的同名文件
+        let final_path = crate::naming::unique_path(&final_path);
+
+        // 只有视频流，无损重新封装为 MP4
+        if video_exists && !audio_exists {
+            println!("只有视频流，封装为最终文件");
+            let remuxed = match self.ffmpeg() {
+                Ok(ffmpeg) => crate::ffmpeg::remux_stream(ffmpeg, &video_path, &final_path).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = remuxed {
+                println!("⚠️ 重新封装失败: {}，直接重命名", e);
+                fs::rename(&video_path, &final_path).map_err(|e| DilidiliError::Filesystem(format!("重命名视频文件失败: {}", e)))?;
+            }
+            return Ok(format!("视频下载完成: {:?}", final_path));
+        }
+
+        // 只有音频流，按编码无损封装为 m4a/flac/eac3
+        if !video_exists && audio_exists {
+            println!("只有音频流，封装为最终文件");
+            crate::ffmpeg::remux_stream(self.ffmpeg()?, &audio_path, &final_path).await?;
+            return Ok(format!("音频下载完成: {:?}", final_path));
+        }
+
+        // 如果都有，进行FFmpeg合并
+        if video_exists && audio_exists {
+            println!("视频和音频都存在，开始FFmpeg合并");
+        
+            // 调用FFmpeg合并，杜比和无损音轨原样复制；失败时音视频流都保留，重试任务时重新合并
+            let merged = crate::ffmpeg::mux_streams(
+                self.ffmpeg()?,
+                &video_path.to_string_lossy(),
+                &audio_path.to_string_lossy(),
+                &final_path.to_string_lossy(),
+                !audio_stream.is_some_and(|a| a.codec.is_premium()),
+            ).await;
+            if let Err(e) = merged {
+                println!("❌ FFmpeg合并失败: {}，已保留下载的音视频流", e);
+                let _ = fs::remove_file(&final_path);
+                return Err(e);
+            }
+            println!("✅ FFmpeg合并成功");
+            return Ok(format!("视频下载并合并完成: {:?}", final_path));
+        }
+
+        // 如果都不存在
+        Err(DilidiliError::Filesystem("下载失败：没有成功下载任何文件".to_string()))
+    }
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+// 命令统一返回的错误类型，序列化为 ErrorInfo 交给前端按 code 区分处理
+#[derive(Debug, Clone)]
+pub enum DilidiliError {
+    // 连接失败、超时、读取中断
+    Network(String),
+    // 非 2xx 的 HTTP 状态码
+    Http { status: u16, message: String },
+    // B站接口返回的非 0 code
+    Api { code: i32, message: String },
+    // 响应或本地数据解析失败
+    Parse(String),
+    // 读写本地文件失败
+    Filesystem(String),
+    // 找不到 ffmpeg 可执行文件
+    FfmpegNotFound,
+    // ffmpeg 执行失败
+    Ffmpeg(String),
+    // 任务被取消
+    Cancelled,
+    // 参数不合法或当前状态不允许该操作
+    InvalidInput(String),
+    // 登录数据使用口令加密，尚未解锁
+    CredentialsLocked,
+}
+
+impl DilidiliError {
+    // 非 2xx 响应，不带额外说明
+    pub fn from_status(status: reqwest::StatusCode) -> Self {
+        Self::Http { status: status.as_u16(), message: String::new() }
+    }
+
+    // 稳定的错误代码，前端据此判断处理方式，不要随意修改
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Network(_) => "network",
+            Self::Http { status, .. } => match status {
+                404 => "not_found",
+                412 | 429 => "rate_limited",
+                _ => "http_status",
+            },
+            Self::Api { code, .. } => match code {
+                -101 => "not_logged_in",
+                -352 => "risk_control",
+                -403 => "access_denied",
+                -404 => "not_found",
+                -412 | -799 => "rate_limited",
+                -10403 => "region_restricted",
+                62002 => "video_invisible",
+                62004 => "video_under_review",
+                62012 => "video_private",
+                87008 => "charge_only",
+                _ => "api_error",
+            },
+            Self::Parse(_) => "parse",
+            Self::Filesystem(_) => "filesystem",
+            Self::FfmpegNotFound => "ffmpeg_not_found",
+            Self::Ffmpeg(_) => "ffmpeg",
+            Self::Cancelled => "cancelled",
+            Self::InvalidInput(_) => "invalid_input",
+            Self::CredentialsLocked => "credentials_locked",
+        }
+    }
+
+    // 稍后重试是否可能成功
+    pub fn retryable(&self) -> bool {
+        match self {
+            Self::Network(_) => true,
+            Self::Http { status, .. } => matches!(status, 408 | 412 | 429) || *status >= 500,
+            Self::Api { code, .. } => matches!(code, -352 | -412 | -500 | -503 | -509 | -799),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for DilidiliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Network(message)
+            | Self::Parse(message)
+            | Self::Filesystem(message)
+            | Self::Ffmpeg(message)
+            | Self::InvalidInput(message) => write!(f, "{}", message),
+            Self::Http { status, message } if message.is_empty() => write!(f, "HTTP 错误: {}", status),
+            Self::Http { status, message } => write!(f, "HTTP 错误: {} - {}", status, message),
+            Self::Api { code, message } => write!(f, "B站接口错误: {} - {}", code, message),
+            Self::FfmpegNotFound => write!(f, "未找到ffmpeg可执行文件"),
+            Self::Cancelled => write!(f, "任务已取消"),
+            Self::CredentialsLocked => write!(f, "登录数据已加密，请先输入口令解锁"),
+        }
+    }
+}
+
+impl std::error::Error for DilidiliError {}
+
+impl From<reqwest::Error> for DilidiliError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => Self::Http { status: status.as_u16(), message: e.to_string() },
+            None if e.is_decode() => Self::Parse(format!("响应解析失败: {}", e)),
+            None => Self::Network(format!("网络请求失败: {}", e)),
+        }
+    }
+}
+
+impl From<serde_json::Error> for DilidiliError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Parse(format!("JSON 解析错误: {}", e))
+    }
+}
+
+impl From<url::ParseError> for DilidiliError {
+    fn from(e: url::ParseError) -> Self {
+        Self::Parse(format!("URL 解析错误: {}", e))
+    }
+}
+
+impl From<std::io::Error> for DilidiliError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Filesystem(e.to_string())
+    }
+}
+
+impl From<tauri_plugin_store::Error> for DilidiliError {
+    fn from(e: tauri_plugin_store::Error) -> Self {
+        Self::Filesystem(format!("读写存储失败: {}", e))
+    }
+}
+
+impl From<tauri::Error> for DilidiliError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Filesystem(e.to_string())
+    }
+}
+
+// 前端收到的错误：code 为稳定的错误代码，message 用于展示
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ErrorInfo {
+    pub code: String,
+    pub message: String,
+    pub retryable: bool,
+    pub status: Option<u16>,   // HTTP 状态码
+    pub api_code: Option<i32>, // B站接口返回的 code
+}
+
+impl From<&DilidiliError> for ErrorInfo {
+    fn from(e: &DilidiliError) -> Self {
+        Self {
+            code: e.code().to_string(),
+            message: e.to_string(),
+            retryable: e.retryable(),
+            status: match e {
+                DilidiliError::Http { status, .. } => Some(*status),
+                _ => None,
+            },
+            api_code: match e {
+                DilidiliError::Api { code, .. } => Some(*code),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Serialize for DilidiliError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ErrorInfo::from(self).serialize(serializer)
+    }
+}
+
+// 旧版本保存的下载队列中错误是纯字符串
+pub fn stored_error<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<ErrorInfo>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Info(ErrorInfo),
+        Message(String),
+    }
+
+    Ok(Option::<Stored>::deserialize(deserializer)?.map(|stored| match stored {
+        Stored::Info(info) => info,
+        Stored::Message(message) => ErrorInfo {
+            code: "unknown".to_string(),
+            message,
+            retryable: true,
+            status: None,
+            api_code: None,
+        },
+    }))
+}
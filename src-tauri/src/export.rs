@@ -1,13 +1,13 @@
+use crate::error::DilidiliError;
 use std::path::Path;
 use std::fs;
 use tauri::Manager;
 
 // 打开文件夹选择器
 #[tauri::command]
-pub async fn select_export_folder(app_handle: tauri::AppHandle) -> Result<String, String> {
+pub async fn select_export_folder(app_handle: tauri::AppHandle) -> Result<String, DilidiliError> {
     // 获取默认下载目录
-    let downloads_dir = app_handle.path().download_dir()
-        .map_err(|e| e.to_string())?;
+    let downloads_dir = app_handle.path().download_dir()?;
     
     // 返回下载目录路径供前端使用
     Ok(downloads_dir.to_string_lossy().to_string())
@@ -19,19 +19,19 @@ pub async fn export_file_to_folder(
     source_path: String,
     target_folder: String,
     new_filename: Option<String>
-) -> Result<String, String> {
+) -> Result<String, DilidiliError> {
     println!("=== 开始导出文件 ===");
     println!("源文件: {}", source_path);
     println!("目标文件夹: {}", target_folder);
     
     let source = Path::new(&source_path);
     if !source.exists() {
-        return Err(format!("源文件不存在: {}", source_path));
+        return Err(DilidiliError::Filesystem(format!("源文件不存在: {}", source_path)));
     }
     
     let target_dir = Path::new(&target_folder);
     if !target_dir.exists() {
-        fs::create_dir_all(target_dir).map_err(|e| format!("创建目标目录失败: {}", e))?;
+        fs::create_dir_all(target_dir).map_err(|e| DilidiliError::Filesystem(format!("创建目标目录失败: {}", e)))?;
     }
     
     // 确定目标文件名
@@ -39,7 +39,7 @@ pub async fn export_file_to_folder(
         new_name
     } else {
         source.file_name()
-            .ok_or("无法获取源文件名")?
+            .ok_or_else(|| DilidiliError::InvalidInput("无法获取源文件名".to_string()))?
             .to_string_lossy()
             .to_string()
     };
@@ -65,7 +65,7 @@ pub async fn export_file_to_folder(
     }
     
     // 复制文件
-    fs::copy(&source, &final_target).map_err(|e| format!("复制文件失败: {}", e))?;
+    fs::copy(&source, &final_target).map_err(|e| DilidiliError::Filesystem(format!("复制文件失败: {}", e)))?;
     
     println!("✅ 文件导出成功: {:?}", final_target);
     Ok(final_target.to_string_lossy().to_string())
@@ -76,14 +76,14 @@ pub async fn export_file_to_folder(
 pub async fn batch_export_files(
     source_files: Vec<String>,
     target_folder: String
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>, DilidiliError> {
     println!("=== 开始批量导出 ===");
     println!("源文件数量: {}", source_files.len());
     println!("目标文件夹: {}", target_folder);
     
     let target_dir = Path::new(&target_folder);
     if !target_dir.exists() {
-        fs::create_dir_all(target_dir).map_err(|e| format!("创建目标目录失败: {}", e))?;
+        fs::create_dir_all(target_dir).map_err(|e| DilidiliError::Filesystem(format!("创建目标目录失败: {}", e)))?;
     }
     
     let mut exported_files = Vec::new();
@@ -102,7 +102,7 @@ pub async fn batch_export_files(
     
     if !errors.is_empty() {
         println!("部分文件导出失败: {:?}", errors);
-        return Err(format!("部分文件导出失败: {}", errors.join(", ")));
+        return Err(DilidiliError::Filesystem(format!("部分文件导出失败: {}", errors.join(", "))));
     }
     
     println!("✅ 批量导出完成，共 {} 个文件", exported_files.len());
@@ -111,11 +111,11 @@ pub async fn batch_export_files(
 
 // 打开文件夹
 #[tauri::command]
-pub async fn open_folder(folder_path: String) -> Result<(), String> {
+pub async fn open_folder(folder_path: String) -> Result<(), DilidiliError> {
     let path = Path::new(&folder_path);
     
     if !path.exists() {
-        return Err(format!("文件夹不存在: {}", folder_path));
+        return Err(DilidiliError::Filesystem(format!("文件夹不存在: {}", folder_path)));
     }
     
     #[cfg(target_os = "macos")]
@@ -123,7 +123,7 @@ pub async fn open_folder(folder_path: String) -> Result<(), String> {
         std::process::Command::new("open")
             .arg(&folder_path)
             .spawn()
-            .map_err(|e| format!("打开文件夹失败: {}", e))?;
+            .map_err(|e| DilidiliError::Filesystem(format!("打开文件夹失败: {}", e)))?;
     }
     
     #[cfg(target_os = "windows")]
@@ -131,7 +131,7 @@ pub async fn open_folder(folder_path: String) -> Result<(), String> {
         std::process::Command::new("explorer")
             .arg(&folder_path)
             .spawn()
-            .map_err(|e| format!("打开文件夹失败: {}", e))?;
+            .map_err(|e| DilidiliError::Filesystem(format!("打开文件夹失败: {}", e)))?;
     }
     
     #[cfg(target_os = "linux")]
@@ -139,7 +139,7 @@ pub async fn open_folder(folder_path: String) -> Result<(), String> {
         std::process::Command::new("xdg-open")
             .arg(&folder_path)
             .spawn()
-            .map_err(|e| format!("打开文件夹失败: {}", e))?;
+            .map_err(|e| DilidiliError::Filesystem(format!("打开文件夹失败: {}", e)))?;
     }
     
     Ok(())
@@ -147,14 +147,14 @@ pub async fn open_folder(folder_path: String) -> Result<(), String> {
 
 // 获取文件信息
 #[tauri::command]
-pub async fn get_file_info(file_path: String) -> Result<FileInfo, String> {
+pub async fn get_file_info(file_path: String) -> Result<FileInfo, DilidiliError> {
     let path = Path::new(&file_path);
     
     if !path.exists() {
-        return Err(format!("文件不存在: {}", file_path));
+        return Err(DilidiliError::Filesystem(format!("文件不存在: {}", file_path)));
     }
     
-    let metadata = fs::metadata(&path).map_err(|e| format!("获取文件信息失败: {}", e))?;
+    let metadata = fs::metadata(&path).map_err(|e| DilidiliError::Filesystem(format!("获取文件信息失败: {}", e)))?;
     
     let file_info = FileInfo {
         path: file_path.clone(),
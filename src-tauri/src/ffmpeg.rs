@@ -1,9 +1,10 @@
+use crate::error::DilidiliError;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use tauri::Manager;
 
 // 获取ffmpeg可执行文件路径
-fn get_ffmpeg_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+pub fn get_ffmpeg_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, DilidiliError> {
     println!("开始查找ffmpeg可执行文件...");
     println!("当前工作目录: {:?}", std::env::current_dir().unwrap_or_default());
     
@@ -57,11 +58,18 @@ fn get_ffmpeg_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
                 println!("  - {:?}", resource_dir.join("ffmpeg"));
             }
             println!("  - 系统PATH");
-            Err("未找到ffmpeg可执行文件".to_string())
+            Err(DilidiliError::FfmpegNotFound)
         }
     }
 }
 
+// 下载流水线中使用的异步 ffmpeg 进程，任务取消时随之结束
+fn ffmpeg_command(ffmpeg_path: &Path) -> tokio::process::Command {
+    let mut cmd = tokio::process::Command::new(ffmpeg_path);
+    cmd.kill_on_drop(true);
+    cmd
+}
+
 // 合并音视频文件
 #[tauri::command]
 pub async fn merge_video_audio(
@@ -69,75 +77,162 @@ pub async fn merge_video_audio(
     video_path: String,
     audio_path: String,
     output_path: String
-) -> Result<String, String> {
+) -> Result<String, DilidiliError> {
+    // 对于B站的M4S文件，使用更兼容的参数
+    let reencode_audio = audio_path.ends_with(".m4s") || audio_path.ends_with(".mp3");
+    let ffmpeg_path = get_ffmpeg_path(&app_handle)?;
+    mux_streams(&ffmpeg_path, &video_path, &audio_path, &output_path, reencode_audio).await
+}
+
+// 用ffmpeg封装音视频，reencode_audio 为 false 时音频原样复制（杜比、无损音轨必须如此）
+pub async fn mux_streams(
+    ffmpeg_path: &Path,
+    video_path: &str,
+    audio_path: &str,
+    output_path: &str,
+    reencode_audio: bool
+) -> Result<String, DilidiliError> {
     println!("=== 开始合并音视频 ===");
     println!("视频文件: {}", video_path);
     println!("音频文件: {}", audio_path);
     println!("输出文件: {}", output_path);
     
-    let ffmpeg_path = get_ffmpeg_path(&app_handle)?;
-    
     // 检查输入文件是否存在
-    if !Path::new(&video_path).exists() {
-        return Err(format!("视频文件不存在: {}", video_path));
+    if !Path::new(video_path).exists() {
+        return Err(DilidiliError::Filesystem(format!("视频文件不存在: {}", video_path)));
     }
-    if !Path::new(&audio_path).exists() {
-        return Err(format!("音频文件不存在: {}", audio_path));
+    if !Path::new(audio_path).exists() {
+        return Err(DilidiliError::Filesystem(format!("音频文件不存在: {}", audio_path)));
     }
     
     // 确保输出目录存在
-    if let Some(parent) = Path::new(&output_path).parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("创建输出目录失败: {}", e))?;
+    if let Some(parent) = Path::new(output_path).parent() {
+        std::fs::create_dir_all(parent).map_err(|e| DilidiliError::Filesystem(format!("创建输出目录失败: {}", e)))?;
     }
     
-    // 先检测音频格式，然后决定处理方式
-    let mut cmd = Command::new(&ffmpeg_path);
+    let mut cmd = ffmpeg_command(ffmpeg_path);
     
-    // 对于B站的M4S文件，使用更兼容的参数
-    if audio_path.ends_with(".m4s") || audio_path.ends_with(".mp3") {
-        cmd.args(&[
-            "-i", &video_path,          // 输入视频
-            "-i", &audio_path,          // 输入音频
+    if reencode_audio {
+        cmd.args([
+            "-i", video_path,           // 输入视频
+            "-i", audio_path,           // 输入音频
             "-c:v", "copy",             // 视频流复制
             "-c:a", "aac",              // 音频重新编码为AAC（更兼容）
             "-b:a", "128k",             // 音频比特率
             "-movflags", "+faststart",   // 优化流媒体播放
             "-avoid_negative_ts", "make_zero", // 避免负时间戳
             "-y",                       // 覆盖输出文件
-            &output_path
+            output_path
+        ]);
+    } else if output_path.ends_with(".mkv") {
+        // MKV 不支持 movflags
+        cmd.args([
+            "-i", video_path,
+            "-i", audio_path,
+            "-c", "copy",
+            "-y",
+            output_path
         ]);
     } else {
-        // 其他格式使用复制模式
-        cmd.args(&[
-            "-i", &video_path,          
-            "-i", &audio_path,          
-            "-c:v", "copy",             
-            "-c:a", "copy",             
-            "-movflags", "+faststart",   
-            "-y",                       
-            &output_path
+        // 其他格式使用复制模式，MP4 中的 FLAC 需要 -strict experimental
+        cmd.args([
+            "-i", video_path,
+            "-i", audio_path,
+            "-c:v", "copy",
+            "-c:a", "copy",
+            "-strict", "experimental",
+            "-movflags", "+faststart",
+            "-y",
+            output_path
         ]);
     }
     
     println!("执行命令: {:?}", cmd);
     
-    let output = cmd.output().map_err(|e| format!("执行ffmpeg失败: {}", e))?;
+    let output = cmd.output().await.map_err(|e| DilidiliError::Ffmpeg(format!("执行ffmpeg失败: {}", e)))?;
     
     if output.status.success() {
         println!("✅ 音视频合并成功");
         println!("输出文件: {}", output_path);
         
         // 删除临时文件
-        let _ = std::fs::remove_file(&video_path);
-        let _ = std::fs::remove_file(&audio_path);
+        let _ = std::fs::remove_file(video_path);
+        let _ = std::fs::remove_file(audio_path);
         
-        Ok(output_path)
+        Ok(output_path.to_string())
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
         println!("ffmpeg错误输出: {}", stderr);
         println!("ffmpeg标准输出: {}", stdout);
-        Err(format!("ffmpeg合并失败: {}", stderr))
+        Err(DilidiliError::Ffmpeg(format!("ffmpeg合并失败: {}", stderr)))
+    }
+}
+
+// 单个流无损重新封装（仅视频/仅音频下载），容器由输出扩展名决定，成功后删除输入文件
+pub async fn remux_stream(ffmpeg_path: &Path, input_path: &Path, output_path: &Path) -> Result<String, DilidiliError> {
+    println!("=== 开始重新封装 ===");
+    println!("输入文件: {:?}", input_path);
+    println!("输出文件: {:?}", output_path);
+
+    let mut cmd = ffmpeg_command(ffmpeg_path);
+    cmd.arg("-i").arg(input_path).args(["-c", "copy"]);
+    // MP4 系容器优化流媒体播放，裸 flac/eac3 不支持 movflags
+    if matches!(output_path.extension().and_then(|e| e.to_str()), Some("mp4" | "m4a")) {
+        cmd.args(["-movflags", "+faststart"]);
+    }
+    cmd.arg("-y").arg(output_path);
+
+    println!("执行命令: {:?}", cmd);
+
+    let output = cmd.output().await.map_err(|e| DilidiliError::Ffmpeg(format!("执行ffmpeg失败: {}", e)))?;
+
+    if output.status.success() {
+        println!("✅ 重新封装成功");
+        let _ = std::fs::remove_file(input_path);
+        Ok(output_path.to_string_lossy().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        println!("ffmpeg错误输出: {}", stderr);
+        Err(DilidiliError::Ffmpeg(format!("ffmpeg封装失败: {}", stderr)))
+    }
+}
+
+// 用ffmpeg concat 拼接多个分段（durl），成功后删除分段文件
+pub async fn concat_segments(ffmpeg_path: &Path, inputs: &[PathBuf], output_path: &Path) -> Result<String, DilidiliError> {
+    println!("=== 开始拼接分段 ===");
+    println!("分段数: {}", inputs.len());
+    println!("输出文件: {:?}", output_path);
+
+    // concat 列表文件，路径中的单引号需要转义
+    let list_path = output_path.with_extension("concat.txt");
+    let list = inputs.iter()
+        .map(|path| format!("file '{}'\n", path.to_string_lossy().replace('\'', "'\\''")))
+        .collect::<String>();
+    std::fs::write(&list_path, list).map_err(|e| DilidiliError::Filesystem(format!("写入分段列表失败: {}", e)))?;
+
+    let mut cmd = ffmpeg_command(ffmpeg_path);
+    cmd.args(["-f", "concat", "-safe", "0", "-i"])
+        .arg(&list_path)
+        .args(["-c", "copy", "-movflags", "+faststart", "-y"])
+        .arg(output_path);
+
+    println!("执行命令: {:?}", cmd);
+
+    let output = cmd.output().await.map_err(|e| DilidiliError::Ffmpeg(format!("执行ffmpeg失败: {}", e)));
+    let _ = std::fs::remove_file(&list_path);
+    let output = output?;
+
+    if output.status.success() {
+        println!("✅ 分段拼接成功");
+        for input in inputs {
+            let _ = std::fs::remove_file(input);
+        }
+        Ok(output_path.to_string_lossy().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        println!("ffmpeg错误输出: {}", stderr);
+        Err(DilidiliError::Ffmpeg(format!("ffmpeg拼接失败: {}", stderr)))
     }
 }
 
@@ -148,7 +243,7 @@ pub async fn convert_video_format(
     input_path: String,
     output_path: String,
     format: String // mp4, avi, mkv等
-) -> Result<String, String> {
+) -> Result<String, DilidiliError> {
     println!("=== 开始转换视频格式 ===");
     println!("输入文件: {}", input_path);
     println!("输出文件: {}", output_path);
@@ -157,12 +252,12 @@ pub async fn convert_video_format(
     let ffmpeg_path = get_ffmpeg_path(&app_handle)?;
     
     if !Path::new(&input_path).exists() {
-        return Err(format!("输入文件不存在: {}", input_path));
+        return Err(DilidiliError::Filesystem(format!("输入文件不存在: {}", input_path)));
     }
     
     // 确保输出目录存在
     if let Some(parent) = Path::new(&output_path).parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("创建输出目录失败: {}", e))?;
+        std::fs::create_dir_all(parent).map_err(|e| DilidiliError::Filesystem(format!("创建输出目录失败: {}", e)))?;
     }
     
     let mut cmd = Command::new(&ffmpeg_path);
@@ -199,13 +294,13 @@ pub async fn convert_video_format(
             ]);
         }
         _ => {
-            return Err(format!("不支持的格式: {}", format));
+            return Err(DilidiliError::InvalidInput(format!("不支持的格式: {}", format)));
         }
     }
     
     println!("执行命令: {:?}", cmd);
     
-    let output = cmd.output().map_err(|e| format!("执行ffmpeg失败: {}", e))?;
+    let output = cmd.output().map_err(|e| DilidiliError::Ffmpeg(format!("执行ffmpeg失败: {}", e)))?;
     
     if output.status.success() {
         println!("✅ 视频格式转换成功");
@@ -213,7 +308,7 @@ pub async fn convert_video_format(
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
         println!("ffmpeg错误输出: {}", stderr);
-        Err(format!("ffmpeg转换失败: {}", stderr))
+        Err(DilidiliError::Ffmpeg(format!("ffmpeg转换失败: {}", stderr)))
     }
 }
 
@@ -224,7 +319,7 @@ pub async fn extract_audio(
     video_path: String,
     audio_path: String,
     format: String // mp3, aac, wav等
-) -> Result<String, String> {
+) -> Result<String, DilidiliError> {
     println!("=== 开始提取音频 ===");
     println!("视频文件: {}", video_path);
     println!("音频文件: {}", audio_path);
@@ -233,12 +328,12 @@ pub async fn extract_audio(
     let ffmpeg_path = get_ffmpeg_path(&app_handle)?;
     
     if !Path::new(&video_path).exists() {
-        return Err(format!("视频文件不存在: {}", video_path));
+        return Err(DilidiliError::Filesystem(format!("视频文件不存在: {}", video_path)));
     }
     
     // 确保输出目录存在
     if let Some(parent) = Path::new(&audio_path).parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("创建输出目录失败: {}", e))?;
+        std::fs::create_dir_all(parent).map_err(|e| DilidiliError::Filesystem(format!("创建输出目录失败: {}", e)))?;
     }
     
     let mut cmd = Command::new(&ffmpeg_path);
@@ -274,13 +369,13 @@ pub async fn extract_audio(
             ]);
         }
         _ => {
-            return Err(format!("不支持的音频格式: {}", format));
+            return Err(DilidiliError::InvalidInput(format!("不支持的音频格式: {}", format)));
         }
     }
     
     println!("执行命令: {:?}", cmd);
     
-    let output = cmd.output().map_err(|e| format!("执行ffmpeg失败: {}", e))?;
+    let output = cmd.output().map_err(|e| DilidiliError::Ffmpeg(format!("执行ffmpeg失败: {}", e)))?;
     
     if output.status.success() {
         println!("✅ 音频提取成功");
@@ -288,7 +383,7 @@ pub async fn extract_audio(
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
         println!("ffmpeg错误输出: {}", stderr);
-        Err(format!("ffmpeg提取音频失败: {}", stderr))
+        Err(DilidiliError::Ffmpeg(format!("ffmpeg提取音频失败: {}", stderr)))
     }
 }
 
@@ -297,16 +392,16 @@ pub async fn extract_audio(
 pub async fn get_local_video_info(
     app_handle: tauri::AppHandle,
     video_path: String
-) -> Result<String, String> {
+) -> Result<String, DilidiliError> {
     let ffmpeg_path = get_ffmpeg_path(&app_handle)?;
     
     if !Path::new(&video_path).exists() {
-        return Err(format!("视频文件不存在: {}", video_path));
+        return Err(DilidiliError::Filesystem(format!("视频文件不存在: {}", video_path)));
     }
     
     // 使用ffprobe获取视频信息
     let ffprobe_path = ffmpeg_path.parent()
-        .ok_or("无法获取ffmpeg父目录")?
+        .ok_or(DilidiliError::FfmpegNotFound)?
         .join("ffprobe");
     
     let mut cmd = Command::new(&ffprobe_path);
@@ -318,13 +413,13 @@ pub async fn get_local_video_info(
         &video_path
     ]);
     
-    let output = cmd.output().map_err(|e| format!("执行ffprobe失败: {}", e))?;
+    let output = cmd.output().map_err(|e| DilidiliError::Ffmpeg(format!("执行ffprobe失败: {}", e)))?;
     
     if output.status.success() {
         let info = String::from_utf8_lossy(&output.stdout);
         Ok(info.to_string())
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("获取视频信息失败: {}", stderr))
+        Err(DilidiliError::Ffmpeg(format!("获取视频信息失败: {}", stderr)))
     }
 } 
\ No newline at end of file
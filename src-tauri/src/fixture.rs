@@ -0,0 +1,204 @@
+use crate::api::BilibiliApi;
+use crate::error::DilidiliError;
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// 录制的响应中媒体地址的占位符，加载时替换为本地媒体服务的地址
+pub const MEDIA_PLACEHOLDER: &str = "{media}";
+
+// 媒体地址未带 size 参数时的文件大小
+const DEFAULT_MEDIA_SIZE: u64 = 256 * 1024;
+
+// 离线的 BilibiliApi：接口返回 fixture 目录中录制的 JSON，媒体流由本地 HTTP 服务提供伪造的字节
+// 文件名：nav.json、view_<bvid>.json、playurl_<bvid>_<cid>.json、qrcode_generate.json、qrcode_poll_<key>.json、
+// cookie_info_<csrf>.json、correspond.html、cookie_refresh_<refresh_token>.json、confirm_refresh_<refresh_token>.json
+//
+// 媒体地址形如 {media}/<路径>?size=<字节数>，支持 Range 请求；
+// 带 status=<状态码> 时直接返回该状态码，用于模拟 CDN 节点失效
+pub struct FixtureApi {
+    dir: PathBuf,
+    media_base: String,
+    media: reqwest::Client,
+    cookies: RwLock<String>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl FixtureApi {
+    // 在 127.0.0.1 的随机端口启动媒体服务，需要在 tokio 运行时中调用
+    pub async fn start(dir: impl Into<PathBuf>) -> Result<Self, DilidiliError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let media_base = format!("http://{}", listener.local_addr()?);
+
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_media(stream));
+            }
+        });
+
+        // 本地服务不走系统代理
+        let media = reqwest::Client::builder().no_proxy().build()?;
+
+        println!("fixture 媒体服务: {}", media_base);
+        Ok(Self { dir: dir.into(), media_base, media, cookies: RwLock::default(), server })
+    }
+
+    // 本地媒体服务的地址，如 http://127.0.0.1:12345
+    pub fn media_base(&self) -> &str {
+        &self.media_base
+    }
+
+    // 模拟已登录的 cookie，如 "SESSDATA=xxx; bili_jct=yyy"
+    pub fn set_cookies(&self, cookies: &str) {
+        *self.cookies.write().unwrap() = cookies.to_string();
+    }
+
+    async fn load(&self, name: String) -> Result<String, DilidiliError> {
+        let path = self.dir.join(&name);
+        let text = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| DilidiliError::Filesystem(format!("读取录制的响应 {:?} 失败: {}", path, e)))?;
+        Ok(text.replace(MEDIA_PLACEHOLDER, &self.media_base))
+    }
+}
+
+impl Drop for FixtureApi {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+impl BilibiliApi for FixtureApi {
+    fn nav(&self) -> BoxFuture<'_, Result<String, DilidiliError>> {
+        self.load("nav.json".to_string()).boxed()
+    }
+
+    fn view<'a>(&'a self, bvid: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        self.load(format!("view_{}.json", bvid)).boxed()
+    }
+
+    fn playurl<'a>(&'a self, bvid: &'a str, cid: u64) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        self.load(format!("playurl_{}_{}.json", bvid, cid)).boxed()
+    }
+
+    fn qrcode_generate(&self) -> BoxFuture<'_, Result<String, DilidiliError>> {
+        self.load("qrcode_generate.json".to_string()).boxed()
+    }
+
+    fn qrcode_poll<'a>(&'a self, qrcode_key: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        self.load(format!("qrcode_poll_{}.json", qrcode_key)).boxed()
+    }
+
+    fn cookie_info<'a>(&'a self, csrf: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        self.load(format!("cookie_info_{}.json", csrf)).boxed()
+    }
+
+    // correspondPath 每次加密结果都不同，只有一个录制的页面
+    fn correspond<'a>(&'a self, _correspond_path: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        self.load("correspond.html".to_string()).boxed()
+    }
+
+    fn cookie_refresh<'a>(&'a self, _csrf: &'a str, _refresh_csrf: &'a str, refresh_token: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        self.load(format!("cookie_refresh_{}.json", refresh_token)).boxed()
+    }
+
+    fn confirm_refresh<'a>(&'a self, _csrf: &'a str, refresh_token: &'a str) -> BoxFuture<'a, Result<String, DilidiliError>> {
+        self.load(format!("confirm_refresh_{}.json", refresh_token)).boxed()
+    }
+
+    fn cookies(&self) -> String {
+        self.cookies.read().unwrap().clone()
+    }
+
+    fn media(&self) -> reqwest::Client {
+        self.media.clone()
+    }
+}
+
+// 媒体文件的伪造内容，由路径和偏移决定，测试据此校验下载结果
+pub fn media_bytes(path: &str, range: std::ops::Range<u64>) -> Vec<u8> {
+    let seed = path.bytes().fold(0u64, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u64));
+    range.map(|offset| (offset.wrapping_mul(7).wrapping_add(seed) % 251) as u8).collect()
+}
+
+// 解析 "bytes=100-" 或 "bytes=100-199"，返回左闭右开区间；超出文件大小时返回 None
+fn parse_range(value: &str, size: u64) -> Option<(u64, u64)> {
+    let (start, end) = value.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let start: u64 = start.trim().parse().ok()?;
+    let end = match end.trim() {
+        "" => size,
+        end => end.parse::<u64>().ok()?.saturating_add(1).min(size),
+    };
+    (start < end).then_some((start, end))
+}
+
+async fn serve_media(mut stream: TcpStream) {
+    if let Err(e) = handle_media(&mut stream).await {
+        println!("⚠️ fixture 媒体服务出错: {}", e);
+    }
+}
+
+async fn handle_media(stream: &mut TcpStream) -> std::io::Result<()> {
+    // 读到空行为止，只处理请求头
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or("/").to_string();
+    let range = lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("range").then(|| value.trim().to_string())
+    });
+
+    let url = url::Url::parse(&format!("http://localhost{}", target))
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let query = |name: &str| url.query_pairs().find(|(key, _)| key == name).and_then(|(_, value)| value.parse::<u64>().ok());
+    let size = query("size").unwrap_or(DEFAULT_MEDIA_SIZE);
+    let path = url.path().to_string();
+
+    let (status, start, end) = match (query("status"), range) {
+        (Some(status), _) => (status, 0, 0),
+        (None, Some(range)) => match parse_range(&range, size) {
+            Some((start, end)) => (206, start, end),
+            None => (416, 0, 0),
+        },
+        (None, None) => (200, 0, size),
+    };
+
+    let reason = match status {
+        200 => "OK",
+        206 => "Partial Content",
+        404 => "Not Found",
+        416 => "Range Not Satisfiable",
+        _ => "Error",
+    };
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: video/mp4\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\nETag: \"{}-{}\"\r\nConnection: close\r\n",
+        status, reason, end - start, path, size
+    );
+    if status == 206 {
+        response.push_str(&format!("Content-Range: bytes {}-{}/{}\r\n", start, end - 1, size));
+    } else if status == 416 {
+        response.push_str(&format!("Content-Range: bytes */{}\r\n", size));
+    }
+    response.push_str("\r\n");
+
+    stream.write_all(response.as_bytes()).await?;
+    if method != "HEAD" {
+        stream.write_all(&media_bytes(&path, start..end)).await?;
+    }
+    stream.shutdown().await
+}
@@ -1,17 +1,47 @@
-mod types;
-mod auth;
-mod video;
-mod download;
+pub mod error;
+pub mod types;
+mod client;
+pub mod api;
+#[cfg(feature = "fixture")]
+pub mod fixture;
+pub mod auth;
+pub mod video;
+pub mod download;
 mod storage;
+mod profiles;
 mod ffmpeg;
 mod export;
+mod manager;
+mod settings;
+mod selector;
+mod pages;
+mod naming;
+mod link;
+mod bvid;
+mod wbi;
+pub mod qr;
+mod ratelimit;
+pub mod refresh;
+pub mod vault;
 
 use auth::*;
 use video::*;
 use download::*;
 use storage::*;
+use profiles::*;
 use ffmpeg::*;
 use export::*;
+use manager::*;
+use settings::*;
+use selector::*;
+use pages::*;
+use link::*;
+use bvid::*;
+use qr::*;
+use refresh::*;
+use vault::*;
+
+use tauri::Manager;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -24,16 +54,70 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_store::Builder::new().build())
+        .manage(DownloadManager::default())
+        .manage(LoginSession::default())
+        .manage(Vault::default())
+        .setup(|app| {
+            let settings = settings::load_settings(app.handle()).unwrap_or_default();
+            ratelimit::init(app.handle(), settings.requests_per_second);
+
+            // 所有请求共用的客户端，带上次保存的登录 cookie
+            let cookies = storage::read_login_data(app.handle())
+                .ok()
+                .flatten()
+                .map(|data| data.cookies)
+                .unwrap_or_default();
+            let client = client::BiliClient::new(&cookies, &settings.proxy).or_else(|e| {
+                println!("⚠️ 代理设置无效，不使用代理: {}", e);
+                client::BiliClient::new(&cookies, &types::ProxySettings::default())
+            })?;
+            storage::persist_cookie_changes(app.handle(), &client, None);
+            app.manage(client);
+
+            // 启动时检查一次登录 cookie，之后定时检查
+            refresh::spawn_scheduler(app.handle().clone());
+
+            // 恢复上次未完成的下载队列
+            let manager = app.state::<DownloadManager>();
+            if let Err(e) = manager.restore(app.handle()) {
+                println!("⚠️ 恢复下载队列失败: {}", e);
+            }
+            manager.schedule(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
-            get_login_qr_code,
-            poll_login_status,
+            start_qr_login,
+            cancel_qr_login,
+            render_qr_code,
+            refresh_login_cookies,
+            get_credential_status,
+            unlock_credentials,
+            set_credential_passphrase,
+            reset_credentials,
+            list_profiles,
+            switch_profile,
+            rename_profile,
+            remove_profile,
             get_user_info,
+            parse_link,
+            convert_video_id,
             get_video_info,
             get_video_streams,
             download_video,
+            enqueue_download,
+            enqueue_auto_download,
+            enqueue_pages,
+            pause_download,
+            resume_download,
+            cancel_download,
+            reorder_download,
+            list_downloads,
+            set_download_concurrency,
+            get_settings,
+            save_settings,
+            select_streams,
             test_stream_url,
-            save_login_data,
             load_login_data,
             clear_login_data,
             merge_video_audio,
@@ -0,0 +1,304 @@
+use crate::client::BiliClient;
+use crate::error::DilidiliError;
+use serde::{Deserialize, Serialize};
+
+// 解析链接得到的目标
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BiliTarget {
+    // 普通视频，av 号链接也转换为 BV 号
+    Video { id: String, page: Option<u32>, start_time: Option<u64> },
+    // 番剧/影视单集
+    Episode { ep_id: u64 },
+    // 番剧/影视整季
+    Season { season_id: u64 },
+    // 番剧/影视条目（md 号）
+    Media { media_id: u64 },
+    // UP主空间
+    Space { mid: u64 },
+    // 收藏夹
+    Favorites { media_id: u64 },
+    // 合集
+    Collection { mid: u64, season_id: u64 },
+    // 视频列表（系列）
+    Series { mid: u64, series_id: u64 },
+    // 直播间
+    Live { room_id: u64 },
+}
+
+// 去掉前缀（不区分大小写）后解析数字，如 "ep123" -> 123
+fn strip_number(value: &str, prefix: &str) -> Option<u64> {
+    let head = value.get(..prefix.len())?;
+    if !head.eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+    value[prefix.len()..].parse().ok()
+}
+
+// BV 号固定为 "BV" + 10 位字母数字
+fn normalize_bvid(value: &str) -> Option<String> {
+    let valid = value.len() == 12
+        && value.chars().all(|c| c.is_ascii_alphanumeric())
+        && value[..2].eq_ignore_ascii_case("BV");
+    valid.then(|| format!("BV{}", &value[2..]))
+}
+
+// 视频 id：BV 号或 av 号，统一转换为 BV 号
+fn video_id(value: &str) -> Option<String> {
+    normalize_bvid(value)
+        .or_else(|| strip_number(value, "av").map(|aid| format!("av{}", aid)))
+        .and_then(|id| crate::bvid::canonical_bvid(&id).ok())
+}
+
+// t 参数可能是 "90"、"90.5" 或 "1m30s"
+fn parse_start_time(value: &str) -> Option<u64> {
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Some(seconds as u64);
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' => {
+                let n: u64 = number.parse().ok()?;
+                total += n * match c { 'h' => 3600, 'm' => 60, _ => 1 };
+                number.clear();
+            }
+            _ => return None,
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(total)
+}
+
+// 不带协议和域名的写法：BV号、av号、ep/ss/md 号
+fn parse_bare_id(input: &str) -> Option<BiliTarget> {
+    if let Some(id) = video_id(input) {
+        return Some(BiliTarget::Video { id, page: None, start_time: None });
+    }
+    if let Some(ep_id) = strip_number(input, "ep") {
+        return Some(BiliTarget::Episode { ep_id });
+    }
+    if let Some(season_id) = strip_number(input, "ss") {
+        return Some(BiliTarget::Season { season_id });
+    }
+    strip_number(input, "md").map(|media_id| BiliTarget::Media { media_id })
+}
+
+// 从分享文案中找出第一个链接，如 "【标题】 https://b23.tv/xxxx"
+fn extract_url(input: &str) -> Option<&str> {
+    let start = input.find("https://").or_else(|| input.find("http://"))?;
+    let rest = &input[start..];
+    let end = rest.find(|c: char| c.is_whitespace() || "】」）)\"'<>".contains(c)).unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+fn is_short_link(url: &url::Url) -> bool {
+    matches!(url.host_str(), Some("b23.tv" | "www.b23.tv" | "bili2233.cn" | "www.bili2233.cn"))
+}
+
+fn to_url(input: &str) -> Option<url::Url> {
+    let input = input.trim();
+    let candidate = extract_url(input).map(str::to_string).unwrap_or_else(|| {
+        // 省略了协议的链接，如 "www.bilibili.com/video/BV..."
+        format!("https://{}", input.trim_start_matches("//"))
+    });
+    url::Url::parse(&candidate).ok().filter(|url| url.host_str().is_some_and(|host| host.contains('.')))
+}
+
+fn query_value(url: &url::Url, key: &str) -> Option<String> {
+    url.query_pairs().find(|(k, _)| k == key).map(|(_, v)| v.into_owned())
+}
+
+// 解析已展开的 bilibili 链接
+fn parse_url(url: &url::Url) -> Option<BiliTarget> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let segments: Vec<&str> = url.path_segments().map(|s| s.filter(|s| !s.is_empty()).collect()).unwrap_or_default();
+
+    if host == "live.bilibili.com" {
+        // live.bilibili.com/123 或 live.bilibili.com/h5/123
+        let room_id = segments.iter().rev().find_map(|s| s.parse().ok())?;
+        return Some(BiliTarget::Live { room_id });
+    }
+
+    if host == "space.bilibili.com" {
+        let mid: u64 = segments.first()?.parse().ok()?;
+        let sid = query_value(url, "sid").and_then(|sid| sid.parse().ok());
+
+        return Some(match segments.get(1..).unwrap_or_default() {
+            ["favlist", ..] => match query_value(url, "fid").and_then(|fid| fid.parse().ok()) {
+                Some(media_id) => BiliTarget::Favorites { media_id },
+                None => BiliTarget::Space { mid },
+            },
+            ["channel", "collectiondetail", ..] => BiliTarget::Collection { mid, season_id: sid? },
+            ["channel", "seriesdetail", ..] => BiliTarget::Series { mid, series_id: sid? },
+            // 新版空间页：/lists/{id}?type=season|series
+            ["lists", id, ..] => {
+                let id = id.parse().ok()?;
+                match query_value(url, "type").as_deref() {
+                    Some("series") => BiliTarget::Series { mid, series_id: id },
+                    _ => BiliTarget::Collection { mid, season_id: id },
+                }
+            }
+            _ => BiliTarget::Space { mid },
+        });
+    }
+
+    if host != "bilibili.com" && !host.ends_with(".bilibili.com") {
+        return None;
+    }
+
+    match segments.as_slice() {
+        // www/m.bilibili.com/video/BV...?p=2&t=30
+        ["video", id, ..] | ["s", "video", id, ..] => Some(BiliTarget::Video {
+            id: video_id(id)?,
+            page: query_value(url, "p").and_then(|p| p.parse().ok()),
+            start_time: query_value(url, "t").and_then(|t| parse_start_time(&t)),
+        }),
+        ["bangumi", "play", id, ..] => parse_bare_id(id).filter(|t| matches!(t, BiliTarget::Episode { .. } | BiliTarget::Season { .. })),
+        ["bangumi", "media", id, ..] => strip_number(id, "md").map(|media_id| BiliTarget::Media { media_id }),
+        // 收藏夹播放页：/medialist/detail/ml123、/medialist/play/ml123、/list/ml123
+        ["medialist", _, id, ..] | ["list", id, ..] => strip_number(id, "ml").map(|media_id| BiliTarget::Favorites { media_id }),
+        _ => None,
+    }
+}
+
+// 跟随重定向展开 b23.tv 短链接
+async fn resolve_short_link(client: &BiliClient, url: &url::Url) -> Result<url::Url, DilidiliError> {
+    println!("展开短链接: {}", url);
+
+    let response = client.api().get(url.as_str())
+        .send()
+        .await
+        .map_err(|e| DilidiliError::Network(format!("短链接请求失败: {}", e)))?;
+
+    let resolved = response.url().clone();
+    println!("短链接指向: {}", resolved);
+    Ok(resolved)
+}
+
+// 解析任意形式的 bilibili 链接，短链接会先展开
+pub async fn resolve_target(client: &BiliClient, input: &str) -> Result<BiliTarget, DilidiliError> {
+    let input = input.trim();
+    if let Some(target) = parse_bare_id(input) {
+        return Ok(target);
+    }
+
+    let mut url = to_url(input).ok_or_else(|| DilidiliError::InvalidInput(format!("无法识别的链接: {}", input)))?;
+    if is_short_link(&url) {
+        url = resolve_short_link(client, &url).await?;
+    }
+    parse_url(&url).ok_or_else(|| DilidiliError::InvalidInput(format!("不支持的链接: {}", url)))
+}
+
+// 解析链接
+#[tauri::command]
+pub async fn parse_link(client: tauri::State<'_, BiliClient>, input: String) -> Result<BiliTarget, DilidiliError> {
+    resolve_target(&client, &input).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BVID: &str = "BV17x411w7KC"; // av170001
+
+    // 与 resolve_target 相同的解析顺序，不展开短链接
+    fn parse(input: &str) -> Option<BiliTarget> {
+        parse_bare_id(input.trim()).or_else(|| parse_url(&to_url(input)?))
+    }
+
+    fn video(page: Option<u32>, start_time: Option<u64>) -> Option<BiliTarget> {
+        Some(BiliTarget::Video { id: BVID.to_string(), page, start_time })
+    }
+
+    #[test]
+    fn parses_video_links() {
+        assert_eq!(parse(BVID), video(None, None));
+        assert_eq!(parse("av170001"), video(None, None));
+        assert_eq!(parse("https://www.bilibili.com/video/BV17x411w7KC/?p=3"), video(Some(3), None));
+        assert_eq!(parse("https://www.bilibili.com/video/av170001?p=2&t=90"), video(Some(2), Some(90)));
+        assert_eq!(parse("www.bilibili.com/video/BV17x411w7KC?t=1m30s"), video(None, Some(90)));
+        assert_eq!(parse("https://m.bilibili.com/video/BV17x411w7KC?p=2"), video(Some(2), None));
+        assert_eq!(parse("https://www.bilibili.com/s/video/BV17x411w7KC"), video(None, None));
+    }
+
+    #[test]
+    fn parses_start_time() {
+        assert_eq!(parse_start_time("90"), Some(90));
+        assert_eq!(parse_start_time("90.5"), Some(90));
+        assert_eq!(parse_start_time("1m30s"), Some(90));
+        assert_eq!(parse_start_time("1h2m3s"), Some(3723));
+        assert_eq!(parse_start_time("1m30"), None);
+        assert_eq!(parse_start_time("abc"), None);
+    }
+
+    #[test]
+    fn parses_bangumi_links() {
+        assert_eq!(parse("ep123"), Some(BiliTarget::Episode { ep_id: 123 }));
+        assert_eq!(parse("https://www.bilibili.com/bangumi/play/ep456?from=search"), Some(BiliTarget::Episode { ep_id: 456 }));
+        assert_eq!(parse("https://m.bilibili.com/bangumi/play/ss789"), Some(BiliTarget::Season { season_id: 789 }));
+        assert_eq!(parse("https://www.bilibili.com/bangumi/media/md28229"), Some(BiliTarget::Media { media_id: 28229 }));
+    }
+
+    #[test]
+    fn parses_space_links() {
+        assert_eq!(parse("https://space.bilibili.com/2/video"), Some(BiliTarget::Space { mid: 2 }));
+        assert_eq!(
+            parse("https://space.bilibili.com/2/favlist?fid=100&ftype=create"),
+            Some(BiliTarget::Favorites { media_id: 100 })
+        );
+        assert_eq!(
+            parse("https://space.bilibili.com/2/channel/collectiondetail?sid=300"),
+            Some(BiliTarget::Collection { mid: 2, season_id: 300 })
+        );
+        assert_eq!(
+            parse("https://space.bilibili.com/2/channel/seriesdetail?sid=400"),
+            Some(BiliTarget::Series { mid: 2, series_id: 400 })
+        );
+        assert_eq!(
+            parse("https://space.bilibili.com/2/lists/500?type=series"),
+            Some(BiliTarget::Series { mid: 2, series_id: 500 })
+        );
+        assert_eq!(
+            parse("https://space.bilibili.com/2/lists/600?type=season"),
+            Some(BiliTarget::Collection { mid: 2, season_id: 600 })
+        );
+        assert_eq!(parse("https://www.bilibili.com/medialist/detail/ml100"), Some(BiliTarget::Favorites { media_id: 100 }));
+    }
+
+    #[test]
+    fn parses_live_rooms() {
+        assert_eq!(parse("https://live.bilibili.com/21452505?spm_id_from=333"), Some(BiliTarget::Live { room_id: 21452505 }));
+        assert_eq!(parse("https://live.bilibili.com/h5/6"), Some(BiliTarget::Live { room_id: 6 }));
+    }
+
+    #[test]
+    fn extracts_url_from_share_text() {
+        let text = "【【官方 MV】Never Gonna Give You Up】 https://www.bilibili.com/video/BV17x411w7KC?p=2 分享自哔哩哔哩";
+        assert_eq!(extract_url(text), Some("https://www.bilibili.com/video/BV17x411w7KC?p=2"));
+        assert_eq!(parse(text), video(Some(2), None));
+        assert_eq!(extract_url("（https://b23.tv/abc123）"), Some("https://b23.tv/abc123"));
+    }
+
+    #[test]
+    fn detects_short_links() {
+        let short = to_url("【标题】 https://b23.tv/abc123").unwrap();
+        assert!(is_short_link(&short));
+        assert!(is_short_link(&to_url("bili2233.cn/xyz").unwrap()));
+        assert!(!is_short_link(&to_url("https://www.bilibili.com/video/BV17x411w7KC").unwrap()));
+    }
+
+    #[test]
+    fn rejects_other_hosts() {
+        assert_eq!(parse("https://www.youtube.com/watch?v=dQw4w9WgXcQ"), None);
+        assert_eq!(parse("https://evilbilibili.com/video/BV17x411w7KC"), None);
+        assert_eq!(parse("https://www.bilibili.com/read/cv123"), None);
+        assert_eq!(parse("hello"), None);
+        assert_eq!(parse("BV17x411w7K"), None);
+    }
+}
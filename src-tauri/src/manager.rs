@@ -0,0 +1,416 @@
+use crate::error::DilidiliError;
+use crate::types::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::async_runtime::JoinHandle;
+use tauri::{Emitter, Manager};
+
+// 任务状态变化事件名
+pub const DOWNLOAD_STATUS_EVENT: &str = "download-status";
+
+// 队列持久化文件
+const QUEUE_STORE: &str = "downloads.json";
+
+// 默认同时进行的下载数
+const DEFAULT_CONCURRENCY: usize = 2;
+
+// 下载队列管理器，作为 Tauri managed state 注册
+pub struct DownloadManager {
+    state: Mutex<QueueState>,
+    next_id: AtomicU64,
+}
+
+struct QueueState {
+    jobs: Vec<DownloadJob>,
+    max_concurrent: usize,
+    handles: HashMap<String, JoinHandle<()>>,
+}
+
+impl Default for DownloadManager {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                jobs: Vec::new(),
+                max_concurrent: DEFAULT_CONCURRENCY,
+                handles: HashMap::new(),
+            }),
+            next_id: AtomicU64::new(0),
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+impl DownloadManager {
+    fn new_job_id(&self) -> String {
+        format!("{}-{}", now_millis(), self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    // 从 downloads.json 恢复队列，上次退出时仍在下载的任务重新排队（依靠 .part 续传）
+    pub fn restore(&self, app_handle: &tauri::AppHandle) -> Result<(), DilidiliError> {
+        use tauri_plugin_store::StoreExt;
+
+        let store = app_handle.store(QUEUE_STORE)?;
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(value) = store.get("max_concurrent") {
+            state.max_concurrent = serde_json::from_value(value).unwrap_or(DEFAULT_CONCURRENCY);
+        }
+        if let Some(value) = store.get("jobs") {
+            let mut jobs: Vec<DownloadJob> = serde_json::from_value(value)?;
+            for job in jobs.iter_mut().filter(|job| job.state == JobState::Running) {
+                job.state = JobState::Queued;
+            }
+            println!("恢复下载队列: {} 个任务", jobs.len());
+            state.jobs = jobs;
+        }
+        Ok(())
+    }
+
+    fn persist(&self, app_handle: &tauri::AppHandle, state: &QueueState) {
+        use tauri_plugin_store::StoreExt;
+
+        let result = app_handle.store(QUEUE_STORE).map_err(DilidiliError::from).and_then(|store| {
+            store.set("jobs", serde_json::to_value(&state.jobs)?);
+            store.set("max_concurrent", state.max_concurrent);
+            Ok(store.save()?)
+        });
+        if let Err(e) = result {
+            println!("⚠️ 保存下载队列失败: {}", e);
+        }
+    }
+
+    // 状态变化后持久化并通知前端
+    fn notify(&self, app_handle: &tauri::AppHandle, state: &QueueState, job_id: &str) {
+        self.persist(app_handle, state);
+        if let Some(job) = state.jobs.iter().find(|job| job.id == job_id) {
+            let _ = app_handle.emit(DOWNLOAD_STATUS_EVENT, job.clone());
+        }
+    }
+
+    // 在并发上限内按队列顺序启动排队中的任务
+    pub fn schedule(&self, app_handle: &tauri::AppHandle) {
+        let mut state = self.state.lock().unwrap();
+
+        // 风控冷却期间不启动新任务，冷却结束后会重新调度
+        if crate::ratelimit::cooling_down() {
+            println!("⏸️ 风控冷却中，暂不启动排队的任务");
+            return;
+        }
+
+        // 设置读取失败时不做路径检查，任务运行时会报告同样的错误
+        let settings = crate::settings::load_settings(app_handle).ok();
+        let claimed = |request: &DownloadRequest| {
+            settings.as_ref()
+                .and_then(|settings| crate::download::claimed_paths(app_handle, settings, request).ok())
+                .map(Vec::from)
+                .unwrap_or_default()
+        };
+        let mut busy: Vec<_> = state.jobs.iter()
+            .filter(|job| job.state == JobState::Running)
+            .flat_map(|job| claimed(&job.request))
+            .collect();
+
+        loop {
+            let running = state.jobs.iter().filter(|job| job.state == JobState::Running).count();
+            if running >= state.max_concurrent {
+                break;
+            }
+            // 跳过与运行中任务写同一临时文件或输出文件的任务，等对方结束后再启动
+            let Some(job) = state.jobs.iter_mut()
+                .filter(|job| job.state == JobState::Queued)
+                .find(|job| claimed(&job.request).iter().all(|path| !busy.contains(path)))
+            else {
+                break;
+            };
+            busy.extend(claimed(&job.request));
+
+            job.state = JobState::Running;
+            job.error = None;
+            let job_id = job.id.clone();
+            let request = job.request.clone();
+
+            let handle = tauri::async_runtime::spawn(run_job(app_handle.clone(), job_id.clone(), request));
+            state.handles.insert(job_id.clone(), handle);
+            self.notify(app_handle, &state, &job_id);
+        }
+    }
+
+    // 任务结束后记录结果，只处理仍处于运行状态的任务（暂停/取消的已由对应命令处理）
+    fn finish(&self, app_handle: &tauri::AppHandle, job_id: &str, result: Result<String, DilidiliError>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.handles.remove(job_id);
+
+            let Some(job) = state.jobs.iter_mut().find(|job| job.id == job_id) else {
+                return;
+            };
+            if job.state != JobState::Running {
+                return;
+            }
+
+            match result {
+                Ok(output) => {
+                    job.state = JobState::Done;
+                    job.progress = 100.0;
+                    job.output = Some(output);
+                }
+                Err(e) => {
+                    println!("❌ 下载任务 {} 失败: {}", job_id, e);
+                    job.state = JobState::Failed;
+                    job.error = Some((&e).into());
+                }
+            }
+            self.notify(app_handle, &state, job_id);
+        }
+
+        self.schedule(app_handle);
+    }
+
+    fn update_progress(&self, job_id: &str, progress: &DownloadProgress) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(job) = state.jobs.iter_mut().find(|job| job.id == job_id) {
+            job.progress = progress.overall;
+        }
+    }
+
+    // 新建任务并加入队列末尾
+    pub fn enqueue(&self, app_handle: &tauri::AppHandle, request: DownloadRequest) -> DownloadJob {
+        let job = DownloadJob {
+            id: self.new_job_id(),
+            title: request.display_title(),
+            state: JobState::Queued,
+            progress: 0.0,
+            error: None,
+            output: None,
+            created_at: now_millis(),
+            request,
+        };
+        println!("加入下载队列: {} ({})", job.title, job.id);
+
+        {
+            let mut state = self.state.lock().unwrap();
+            state.jobs.push(job.clone());
+            self.notify(app_handle, &state, &job.id);
+        }
+        self.schedule(app_handle);
+
+        job
+    }
+
+    fn abort(state: &mut QueueState, job_id: &str) {
+        if let Some(handle) = state.handles.remove(job_id) {
+            handle.abort();
+        }
+    }
+}
+
+// 在后台执行一个下载任务
+async fn run_job(app_handle: tauri::AppHandle, job_id: String, request: DownloadRequest) {
+    let result = crate::download::run_download(&app_handle, &request, &job_id, |progress| {
+        app_handle.state::<DownloadManager>().update_progress(&job_id, &progress);
+        let _ = app_handle.emit(crate::download::DOWNLOAD_PROGRESS_EVENT, progress);
+    }).await;
+
+    app_handle.state::<DownloadManager>().finish(&app_handle, &job_id, result);
+}
+
+// 添加下载任务到队列
+#[tauri::command]
+pub async fn enqueue_download(
+    app_handle: tauri::AppHandle,
+    manager: tauri::State<'_, DownloadManager>,
+    request: DownloadRequest
+) -> Result<DownloadJob, DilidiliError> {
+    Ok(manager.enqueue(&app_handle, request))
+}
+
+// 按设置中的选流规则自动选择视频流和音频流后加入队列
+#[tauri::command]
+pub async fn enqueue_auto_download(
+    app_handle: tauri::AppHandle,
+    manager: tauri::State<'_, DownloadManager>,
+    video_data: VideoData,
+    cid: Option<u64>,
+    mode: Option<DownloadMode>,
+    options: Option<DownloadOptions>,
+    profile_id: Option<String>
+) -> Result<DownloadJob, DilidiliError> {
+    let request = auto_request(&app_handle, video_data, cid, mode.unwrap_or_default(), options.unwrap_or_default(), profile_id).await?;
+    Ok(manager.enqueue(&app_handle, request))
+}
+
+// 获取流列表并自动选流，生成下载请求；profile_id 指定用哪个账号获取流（影响可选的清晰度）
+pub async fn auto_request(
+    app_handle: &tauri::AppHandle,
+    video_data: VideoData,
+    cid: Option<u64>,
+    mode: DownloadMode,
+    options: DownloadOptions,
+    profile_id: Option<String>
+) -> Result<DownloadRequest, DilidiliError> {
+    let cid = cid
+        .or_else(|| video_data.pages.first().map(|page| page.cid))
+        .ok_or_else(|| DilidiliError::InvalidInput("视频没有可下载的分P".to_string()))?;
+    let duration = video_data.pages.iter()
+        .find(|page| page.cid == cid)
+        .map(|page| page.duration)
+        .unwrap_or(video_data.duration);
+
+    let client = crate::profiles::client_for_profile(app_handle, profile_id.as_deref())?;
+    let streams = crate::video::fetch_video_streams(&client, &video_data.bvid, cid).await?;
+
+    let settings = crate::settings::load_settings(app_handle)?;
+    let (video_stream, audio_stream) = crate::selector::StreamSelector::new(&settings.selection)
+        .select(&streams, duration)?;
+
+    if mode == DownloadMode::AudioOnly && audio_stream.is_none() {
+        return Err(DilidiliError::InvalidInput("该视频没有独立的音频流，无法仅下载音频".to_string()));
+    }
+
+    Ok(DownloadRequest {
+        video_data,
+        video_stream: Some(video_stream),
+        audio_stream,
+        cid: Some(cid),
+        mode,
+        options,
+        profile_id,
+    })
+}
+
+// 暂停任务，已下载的部分保留在 .part 文件中
+#[tauri::command]
+pub async fn pause_download(
+    app_handle: tauri::AppHandle,
+    manager: tauri::State<'_, DownloadManager>,
+    job_id: String
+) -> Result<(), DilidiliError> {
+    {
+        let mut state = manager.state.lock().unwrap();
+        let job = state.jobs.iter_mut()
+            .find(|job| job.id == job_id)
+            .ok_or_else(|| DilidiliError::InvalidInput(format!("下载任务不存在: {}", job_id)))?;
+
+        if !matches!(job.state, JobState::Queued | JobState::Running) {
+            return Err(DilidiliError::InvalidInput("只能暂停排队中或下载中的任务".to_string()));
+        }
+        job.state = JobState::Paused;
+
+        DownloadManager::abort(&mut state, &job_id);
+        manager.notify(&app_handle, &state, &job_id);
+    }
+    manager.schedule(&app_handle);
+
+    Ok(())
+}
+
+// 恢复暂停或失败的任务，重新排队
+#[tauri::command]
+pub async fn resume_download(
+    app_handle: tauri::AppHandle,
+    manager: tauri::State<'_, DownloadManager>,
+    job_id: String
+) -> Result<(), DilidiliError> {
+    {
+        let mut state = manager.state.lock().unwrap();
+        let job = state.jobs.iter_mut()
+            .find(|job| job.id == job_id)
+            .ok_or_else(|| DilidiliError::InvalidInput(format!("下载任务不存在: {}", job_id)))?;
+
+        if !matches!(job.state, JobState::Paused | JobState::Failed) {
+            return Err(DilidiliError::InvalidInput("只能恢复已暂停或失败的任务".to_string()));
+        }
+        job.state = JobState::Queued;
+        job.error = None;
+
+        manager.notify(&app_handle, &state, &job_id);
+    }
+    manager.schedule(&app_handle);
+
+    Ok(())
+}
+
+// 取消任务：停止下载、移出队列并删除未完成的临时文件
+#[tauri::command]
+pub async fn cancel_download(
+    app_handle: tauri::AppHandle,
+    manager: tauri::State<'_, DownloadManager>,
+    job_id: String
+) -> Result<(), DilidiliError> {
+    let mut job = {
+        let mut state = manager.state.lock().unwrap();
+        let index = state.jobs.iter()
+            .position(|job| job.id == job_id)
+            .ok_or_else(|| DilidiliError::InvalidInput(format!("下载任务不存在: {}", job_id)))?;
+
+        DownloadManager::abort(&mut state, &job_id);
+        let job = state.jobs.remove(index);
+        manager.persist(&app_handle, &state);
+        job
+    };
+
+    if job.state != JobState::Done {
+        crate::download::remove_partial_files(&app_handle, &job.request)?;
+    }
+    println!("已取消下载任务: {} ({})", job.title, job.id);
+
+    // 任务已移出队列，最后通知一次前端
+    job.error = Some((&DilidiliError::Cancelled).into());
+    let _ = app_handle.emit(DOWNLOAD_STATUS_EVENT, job);
+
+    manager.schedule(&app_handle);
+    Ok(())
+}
+
+// 调整任务在队列中的位置
+#[tauri::command]
+pub async fn reorder_download(
+    app_handle: tauri::AppHandle,
+    manager: tauri::State<'_, DownloadManager>,
+    job_id: String,
+    new_index: usize
+) -> Result<Vec<DownloadJob>, DilidiliError> {
+    let mut state = manager.state.lock().unwrap();
+    let index = state.jobs.iter()
+        .position(|job| job.id == job_id)
+        .ok_or_else(|| DilidiliError::InvalidInput(format!("下载任务不存在: {}", job_id)))?;
+
+    let job = state.jobs.remove(index);
+    let new_index = new_index.min(state.jobs.len());
+    state.jobs.insert(new_index, job);
+    manager.persist(&app_handle, &state);
+
+    Ok(state.jobs.clone())
+}
+
+// 列出队列中的所有任务
+#[tauri::command]
+pub async fn list_downloads(manager: tauri::State<'_, DownloadManager>) -> Result<Vec<DownloadJob>, DilidiliError> {
+    Ok(manager.state.lock().unwrap().jobs.clone())
+}
+
+// 设置同时下载的任务数
+#[tauri::command]
+pub async fn set_download_concurrency(
+    app_handle: tauri::AppHandle,
+    manager: tauri::State<'_, DownloadManager>,
+    limit: usize
+) -> Result<(), DilidiliError> {
+    if limit == 0 {
+        return Err(DilidiliError::InvalidInput("并发数至少为 1".to_string()));
+    }
+
+    {
+        let mut state = manager.state.lock().unwrap();
+        state.max_concurrent = limit;
+        manager.persist(&app_handle, &state);
+    }
+    manager.schedule(&app_handle);
+
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use crate::types::*;
+use std::path::{Path, PathBuf};
+
+// 单个路径组件的最大字节数，给临时文件后缀和重名序号留出余量（多数文件系统上限为 255）
+const MAX_COMPONENT_BYTES: usize = 200;
+
+// Windows 保留设备名，不区分大小写，带扩展名时同样不可用
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// 清理一个路径组件：替换各平台不允许的字符，处理保留名、首尾空格和点、长度限制；中日韩字符原样保留
+pub fn sanitize_component(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    // Windows 不允许以空格或点结尾
+    let trimmed = replaced.trim().trim_end_matches(['.', ' ']);
+    let mut name = truncate_bytes(trimmed, MAX_COMPONENT_BYTES).trim_end_matches(['.', ' ']).to_string();
+
+    if name.is_empty() || name.chars().all(|c| c == '.') {
+        return "_".to_string();
+    }
+
+    let stem = name.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+// 按字节截断，不拆开多字节字符
+fn truncate_bytes(value: &str, max: usize) -> &str {
+    if value.len() <= max {
+        return value;
+    }
+    let mut end = max;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+// unix 秒转为 YYYY-MM-DD（北京时间）
+fn format_date(timestamp: u64) -> String {
+    let days = (timestamp + 8 * 3600) / 86400;
+
+    // 公历日期换算，见 http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// 模板占位符的取值
+fn placeholder_value(name: &str, request: &DownloadRequest) -> Option<String> {
+    let video_data = &request.video_data;
+    let page = request.page();
+    let (video, audio) = request.selected_streams();
+
+    let value = match name {
+        "title" => video_data.title.clone(),
+        "bvid" => video_data.bvid.clone(),
+        "aid" => video_data.aid.to_string(),
+        "author" => video_data.owner_info.name.clone(),
+        "mid" => video_data.owner_info.mid.to_string(),
+        "page" => page.map(|p| p.page).unwrap_or(1).to_string(),
+        "part" => page.map(|p| p.part.clone()).unwrap_or_default(),
+        "quality" => match (video, audio) {
+            (Some(video), _) => video.description.clone(),
+            (None, Some(audio)) => audio.description.clone(),
+            (None, None) => String::new(),
+        },
+        "codec" => match (video, audio) {
+            (Some(video), _) => video.codec.name().to_string(),
+            (None, Some(audio)) => audio.codec.name().to_string(),
+            (None, None) => String::new(),
+        },
+        "pubdate" => format_date(video_data.pubdate),
+        _ => return None,
+    };
+    Some(value)
+}
+
+// 展开文件名模板，如 "{author}/{title} [{bvid}]"
+// 模板中的 / 或 \ 表示子目录；占位符的值里的斜杠不会产生目录。未知占位符原样保留
+pub fn render_template(template: &str, request: &DownloadRequest) -> PathBuf {
+    let mut rendered = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) => {
+                let name = &after[..end];
+                match placeholder_value(name.trim(), request) {
+                    Some(value) => rendered.push_str(&value.replace(['/', '\\'], "_")),
+                    None => {
+                        rendered.push('{');
+                        rendered.push_str(name);
+                        rendered.push('}');
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                rendered.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    let components: Vec<String> = rendered
+        .split(['/', '\\'])
+        .filter(|component| !component.trim().is_empty())
+        .map(sanitize_component)
+        .collect();
+
+    if components.is_empty() {
+        return PathBuf::from(sanitize_component(&request.video_data.bvid));
+    }
+    components.iter().collect()
+}
+
+// 目标文件已存在时追加 " (1)"、" (2)" 等序号
+pub fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(title: &str, author: &str, pubdate: u64) -> DownloadRequest {
+        DownloadRequest {
+            video_data: VideoData {
+                bvid: "BV17x411w7KC".to_string(),
+                aid: 170001,
+                title: title.to_string(),
+                desc: String::new(),
+                pic: String::new(),
+                owner_info: VideoOwner { name: author.to_string(), face: None, mid: 2 },
+                duration: 60,
+                pages: vec![VideoPage { cid: 1, page: 1, part: "P1".to_string(), duration: 60 }],
+                pubdate,
+            },
+            video_stream: None,
+            audio_stream: None,
+            cid: None,
+            mode: DownloadMode::default(),
+            options: DownloadOptions::default(),
+            profile_id: None,
+        }
+    }
+
+    #[test]
+    fn escapes_reserved_names() {
+        assert_eq!(sanitize_component("CON"), "_CON");
+        assert_eq!(sanitize_component("con.mp4"), "_con.mp4");
+        assert_eq!(sanitize_component("COM1"), "_COM1");
+        assert_eq!(sanitize_component("lpt1.txt"), "_lpt1.txt");
+        assert_eq!(sanitize_component("CONSOLE"), "CONSOLE");
+        assert_eq!(sanitize_component("COM10"), "COM10");
+    }
+
+    #[test]
+    fn replaces_invalid_characters_and_trailing_dots() {
+        assert_eq!(sanitize_component("a<b>c:d\"e|f?g*h"), "a_b_c_d_e_f_g_h");
+        assert_eq!(sanitize_component("name. . "), "name");
+        assert_eq!(sanitize_component("  padded  "), "padded");
+        assert_eq!(sanitize_component("..."), "_");
+        assert_eq!(sanitize_component(""), "_");
+    }
+
+    #[test]
+    fn truncates_on_char_boundary() {
+        let long = format!("a{}", "中".repeat(70));
+        let truncated = sanitize_component(&long);
+        assert_eq!(truncated.len(), 199);
+        assert_eq!(truncated, format!("a{}", "中".repeat(66)));
+    }
+
+    #[test]
+    fn keeps_cjk_text() {
+        assert_eq!(sanitize_component("【官方 MV】一起去看日出吧！"), "【官方 MV】一起去看日出吧！");
+        assert_eq!(sanitize_component("東方 / 幻想郷"), "東方 _ 幻想郷");
+    }
+
+    #[test]
+    fn renders_subdirectories() {
+        let request = request("Never Gonna Give You Up", "Rick", 0);
+        assert_eq!(
+            render_template("{author}/{title} [{bvid}]", &request),
+            PathBuf::from("Rick").join("Never Gonna Give You Up [BV17x411w7KC]")
+        );
+        // 占位符的值中的斜杠不产生目录，未知占位符原样保留
+        let request = self::request("AC/DC", "a\\b", 0);
+        assert_eq!(render_template("{author}/{title} {unknown}", &request), PathBuf::from("a_b").join("AC_DC {unknown}"));
+    }
+
+    #[test]
+    fn neutralises_traversal() {
+        let request = request("..", "..", 0);
+        let path = render_template("../{author}/{title}/./x", &request);
+        assert_eq!(path, ["_", "_", "_", "_", "x"].iter().collect::<PathBuf>());
+        assert!(path.components().all(|c| matches!(c, std::path::Component::Normal(_))));
+        assert_eq!(render_template("///", &request), PathBuf::from("BV17x411w7KC"));
+    }
+
+    #[test]
+    fn formats_pubdate_in_beijing_time() {
+        // 2024-01-01 00:00:00 +08:00
+        assert_eq!(format_date(1704038400), "2024-01-01");
+        assert_eq!(format_date(1704038399), "2023-12-31");
+        assert_eq!(render_template("{pubdate}", &request("t", "a", 1709222400)), PathBuf::from("2024-03-01"));
+    }
+
+    #[test]
+    fn appends_suffix_on_collision() {
+        let dir = std::env::temp_dir().join(format!("dilidili-naming-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("video.mp4");
+        assert_eq!(unique_path(&path), path);
+        std::fs::write(&path, b"").unwrap();
+        assert_eq!(unique_path(&path), dir.join("video (1).mp4"));
+        std::fs::write(dir.join("video (1).mp4"), b"").unwrap();
+        assert_eq!(unique_path(&path), dir.join("video (2).mp4"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::client::BiliClient;
+use crate::error::DilidiliError;
+use crate::manager::DownloadManager;
+use crate::types::*;
+
+// 解析分P选择，如 "1-3,5,8-"；空字符串、"all" 或 "*" 表示全部分P
+// 返回升序去重后的分P序号（从 1 开始）
+pub fn parse_page_selection(spec: &str, page_count: u32) -> Result<Vec<u32>, DilidiliError> {
+    if page_count == 0 {
+        return Err(DilidiliError::InvalidInput("视频没有分P".to_string()));
+    }
+
+    let spec = spec.trim();
+    if spec.is_empty() || spec == "*" || spec.eq_ignore_ascii_case("all") {
+        return Ok((1..=page_count).collect());
+    }
+
+    let parse_number = |value: &str| -> Result<u32, DilidiliError> {
+        value.trim().parse::<u32>().map_err(|_| DilidiliError::InvalidInput(format!("无效的分P序号: {}", value.trim())))
+    };
+
+    let mut pages = Vec::new();
+    for item in spec.split([',', '，']).map(str::trim).filter(|item| !item.is_empty()) {
+        let (start, end) = match item.split_once('-') {
+            // "8-" 表示到最后一P，"-3" 表示从第一P开始
+            Some((start, end)) => (
+                if start.trim().is_empty() { 1 } else { parse_number(start)? },
+                if end.trim().is_empty() { page_count } else { parse_number(end)? },
+            ),
+            None => {
+                let page = parse_number(item)?;
+                (page, page)
+            }
+        };
+
+        if start == 0 || start > end {
+            return Err(DilidiliError::InvalidInput(format!("无效的分P范围: {}", item)));
+        }
+        if end > page_count {
+            return Err(DilidiliError::InvalidInput(format!("分P范围 {} 超出视频总P数 {}", item, page_count)));
+        }
+        pages.extend(start..=end);
+    }
+
+    if pages.is_empty() {
+        return Err(DilidiliError::InvalidInput("没有选择任何分P".to_string()));
+    }
+    pages.sort_unstable();
+    pages.dedup();
+    Ok(pages)
+}
+
+// 批量下载多P视频：按分P选择逐个获取流地址、自动选流并加入下载队列
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn enqueue_pages(
+    app_handle: tauri::AppHandle,
+    manager: tauri::State<'_, DownloadManager>,
+    client: tauri::State<'_, BiliClient>,
+    video_id: String,
+    pages: String,
+    mode: Option<DownloadMode>,
+    options: Option<DownloadOptions>,
+    profile_id: Option<String>
+) -> Result<EnqueuePagesResult, DilidiliError> {
+    let bvid = crate::video::resolve_video_id(&client, &video_id).await?;
+    let video_data = crate::video::fetch_video_info(client.inner(), &bvid).await?;
+    let selected = parse_page_selection(&pages, video_data.pages.len() as u32)?;
+    println!("批量下载 {}: 选择了 {} 个分P {:?}", video_data.title, selected.len(), selected);
+
+    let mode = mode.unwrap_or_default();
+    let options = options.unwrap_or_default();
+    let mut jobs = Vec::new();
+    let mut errors = Vec::new();
+
+    for page in selected {
+        let Some(video_page) = video_data.pages.iter().find(|p| p.page == page) else {
+            errors.push((page, String::new(), DilidiliError::InvalidInput("分P不存在".to_string())));
+            continue;
+        };
+
+        match crate::manager::auto_request(&app_handle, video_data.clone(), Some(video_page.cid), mode, options.clone(), profile_id.clone()).await {
+            Ok(request) => jobs.push(manager.enqueue(&app_handle, request)),
+            Err(e) => {
+                println!("⚠️ P{} {} 获取流失败: {}", page, video_page.part, e);
+                errors.push((page, video_page.part.clone(), e));
+            }
+        }
+    }
+
+    // 全部失败时返回第一个错误，前端可以据此判断是否需要登录等
+    if jobs.is_empty() && !errors.is_empty() {
+        return Err(errors.swap_remove(0).2);
+    }
+    if !errors.is_empty() {
+        println!("⚠️ {} 个分P未加入队列", errors.len());
+    }
+    let failed = errors.into_iter()
+        .map(|(page, part, e)| PageFailure { page, part, error: (&e).into() })
+        .collect();
+    Ok(EnqueuePagesResult { jobs, failed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pages(spec: &str, count: u32) -> Vec<u32> {
+        parse_page_selection(spec, count).unwrap()
+    }
+
+    #[test]
+    fn parses_ranges_and_lists() {
+        assert_eq!(pages("1-3,5,8-", 10), vec![1, 2, 3, 5, 8, 9, 10]);
+        assert_eq!(pages("-3", 10), vec![1, 2, 3]);
+        assert_eq!(pages("2，4 , 6", 10), vec![2, 4, 6]);
+        assert_eq!(pages(" 7 - 8 ", 10), vec![7, 8]);
+    }
+
+    #[test]
+    fn selects_all_pages() {
+        assert_eq!(pages("", 3), vec![1, 2, 3]);
+        assert_eq!(pages("all", 3), vec![1, 2, 3]);
+        assert_eq!(pages("ALL", 3), vec![1, 2, 3]);
+        assert_eq!(pages("*", 3), vec![1, 2, 3]);
+        assert_eq!(pages("-", 3), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn sorts_and_dedups() {
+        assert_eq!(pages("5,1-3,2,3-4,1", 10), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn rejects_invalid_selection() {
+        for spec in ["0", "0-2", "5-3", "11", "9-11", "a", "1,b", "1-x", "1.5", ",", "1--3"] {
+            let error = parse_page_selection(spec, 10).unwrap_err();
+            assert_eq!(error.code(), "invalid_input", "{}", spec);
+        }
+        assert!(parse_page_selection("1", 0).is_err());
+    }
+}
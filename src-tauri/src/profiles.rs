@@ -0,0 +1,110 @@
+use crate::client::BiliClient;
+use crate::error::DilidiliError;
+use crate::storage::{load_profiles, save_profiles};
+use crate::types::*;
+use tauri::Manager;
+
+fn summaries(profiles: &[AccountProfile], active: Option<&str>) -> Vec<ProfileSummary> {
+    profiles.iter()
+        .map(|profile| ProfileSummary {
+            id: profile.id.clone(),
+            name: profile.name.clone(),
+            user_profile: profile.login_data.user_profile.clone(),
+            login_time: profile.login_data.login_time,
+            active: active == Some(profile.id.as_str()),
+        })
+        .collect()
+}
+
+fn not_found(profile_id: &str) -> DilidiliError {
+    DilidiliError::InvalidInput(format!("账号不存在: {}", profile_id))
+}
+
+// 指定账号使用的客户端：未指定或就是当前账号时使用共享的客户端，否则用该账号的 cookie 新建
+pub fn client_for_profile(app_handle: &tauri::AppHandle, profile_id: Option<&str>) -> Result<BiliClient, DilidiliError> {
+    let client = app_handle.state::<BiliClient>().inner().clone();
+    let Some(profile_id) = profile_id else {
+        return Ok(client);
+    };
+
+    let (profiles, active) = load_profiles(app_handle)?;
+    if active.as_deref() == Some(profile_id) {
+        return Ok(client);
+    }
+    let profile = profiles.iter()
+        .find(|profile| profile.id == profile_id)
+        .ok_or_else(|| not_found(profile_id))?;
+    let client = client.with_cookies(&profile.login_data.cookies)?;
+    crate::storage::persist_cookie_changes(app_handle, &client, Some(profile.id.clone()));
+    Ok(client)
+}
+
+// 获取保存的账号列表
+#[tauri::command]
+pub async fn list_profiles(app_handle: tauri::AppHandle) -> Result<Vec<ProfileSummary>, DilidiliError> {
+    let (profiles, active) = load_profiles(&app_handle)?;
+    Ok(summaries(&profiles, active.as_deref()))
+}
+
+// 切换当前账号，返回该账号的登录信息
+#[tauri::command]
+pub async fn switch_profile(
+    app_handle: tauri::AppHandle,
+    client: tauri::State<'_, BiliClient>,
+    profile_id: String
+) -> Result<LoginInfo, DilidiliError> {
+    let (profiles, _) = load_profiles(&app_handle)?;
+    let profile = profiles.iter()
+        .find(|profile| profile.id == profile_id)
+        .ok_or_else(|| not_found(&profile_id))?;
+
+    save_profiles(&app_handle, &profiles, Some(&profile.id))?;
+    client.cookie_jar().load(&profile.login_data.cookies);
+
+    println!("切换到账号: {}", profile.name);
+    Ok((&profile.login_data).into())
+}
+
+// 重命名账号
+#[tauri::command]
+pub async fn rename_profile(
+    app_handle: tauri::AppHandle,
+    profile_id: String,
+    name: String
+) -> Result<Vec<ProfileSummary>, DilidiliError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(DilidiliError::InvalidInput("账号名称不能为空".to_string()));
+    }
+
+    let (mut profiles, active) = load_profiles(&app_handle)?;
+    let profile = profiles.iter_mut()
+        .find(|profile| profile.id == profile_id)
+        .ok_or_else(|| not_found(&profile_id))?;
+    profile.name = name.to_string();
+
+    save_profiles(&app_handle, &profiles, active.as_deref())?;
+    Ok(summaries(&profiles, active.as_deref()))
+}
+
+// 删除账号，删除的是当前账号时变为未登录
+#[tauri::command]
+pub async fn remove_profile(
+    app_handle: tauri::AppHandle,
+    client: tauri::State<'_, BiliClient>,
+    profile_id: String
+) -> Result<Vec<ProfileSummary>, DilidiliError> {
+    let (mut profiles, mut active) = load_profiles(&app_handle)?;
+    let count = profiles.len();
+    profiles.retain(|profile| profile.id != profile_id);
+    if profiles.len() == count {
+        return Err(not_found(&profile_id));
+    }
+
+    if active.as_deref() == Some(profile_id.as_str()) {
+        active = None;
+        client.cookie_jar().clear();
+    }
+    save_profiles(&app_handle, &profiles, active.as_deref())?;
+    Ok(summaries(&profiles, active.as_deref()))
+}
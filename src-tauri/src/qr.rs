@@ -0,0 +1,97 @@
+use crate::error::DilidiliError;
+use crate::types::*;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use qrcode::render::{svg, unicode};
+use qrcode::{Color, EcLevel, QrCode};
+
+// 图片边长的范围（像素）
+const MIN_SIZE: u32 = 64;
+const MAX_SIZE: u32 = 2048;
+
+// 二维码四周留白的模块数，规范要求至少 4 个
+const QUIET_ZONE: u32 = 4;
+
+fn encode(content: &str, ec_level: QrEcLevel) -> Result<QrCode, DilidiliError> {
+    let level = match ec_level {
+        QrEcLevel::L => EcLevel::L,
+        QrEcLevel::M => EcLevel::M,
+        QrEcLevel::Q => EcLevel::Q,
+        QrEcLevel::H => EcLevel::H,
+    };
+    QrCode::with_error_correction_level(content, level)
+        .map_err(|e| DilidiliError::InvalidInput(format!("无法生成二维码: {}", e)))
+}
+
+fn check_size(size: u32) -> Result<(), DilidiliError> {
+    if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+        return Err(DilidiliError::InvalidInput(format!("二维码尺寸应在 {} 到 {} 像素之间", MIN_SIZE, MAX_SIZE)));
+    }
+    Ok(())
+}
+
+// 渲染为 SVG 和 PNG 两种 data URI
+pub fn render_image(content: &str, options: &QrRenderOptions) -> Result<QrImage, DilidiliError> {
+    check_size(options.size)?;
+    let code = encode(content, options.ec_level)?;
+    Ok(QrImage {
+        svg: format!("data:image/svg+xml;base64,{}", BASE64.encode(render_svg(&code, options.size))),
+        png: format!("data:image/png;base64,{}", BASE64.encode(render_png(&code, options.size)?)),
+    })
+}
+
+fn render_svg(code: &QrCode, size: u32) -> String {
+    code.render::<svg::Color>()
+        .quiet_zone(true)
+        .min_dimensions(size, size)
+        .build()
+}
+
+// 每个模块放大为整数像素，边长不小于 size
+fn render_png(code: &QrCode, size: u32) -> Result<Vec<u8>, DilidiliError> {
+    let modules = code.width() as u32 + QUIET_ZONE * 2;
+    let scale = size.div_ceil(modules);
+    let side = modules * scale;
+    let colors = code.to_colors();
+
+    let mut pixels = vec![0xffu8; (side * side) as usize];
+    for y in 0..side {
+        let row = y / scale;
+        for x in 0..side {
+            let col = x / scale;
+            let inside = (QUIET_ZONE..modules - QUIET_ZONE).contains(&row) && (QUIET_ZONE..modules - QUIET_ZONE).contains(&col);
+            if inside {
+                let index = ((row - QUIET_ZONE) * code.width() as u32 + (col - QUIET_ZONE)) as usize;
+                if colors[index] == Color::Dark {
+                    pixels[(y * side + x) as usize] = 0;
+                }
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, side, side);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|e| DilidiliError::Parse(format!("PNG 编码失败: {}", e)))?;
+    Ok(png)
+}
+
+// 用半高的 Unicode 方块渲染，一行字符对应两行模块，可以直接打印到终端扫码
+// 终端一般是深色背景，颜色反转后深色模块显示为背景色
+pub fn render_terminal(content: &str, ec_level: QrEcLevel) -> Result<String, DilidiliError> {
+    let code = encode(content, ec_level)?;
+    Ok(code.render::<unicode::Dense1x2>()
+        .quiet_zone(true)
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build())
+}
+
+// 把任意文本渲染为二维码图片
+#[tauri::command]
+pub async fn render_qr_code(content: String, options: Option<QrRenderOptions>) -> Result<QrImage, DilidiliError> {
+    render_image(&content, &options.unwrap_or_default())
+}
@@ -0,0 +1,269 @@
+use crate::error::DilidiliError;
+use crate::types::*;
+use serde::Deserialize;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tokio::time::Instant;
+
+// 风控冷却开始/结束事件名
+pub const API_COOLDOWN_EVENT: &str = "api-cooldown";
+
+// 默认每秒最多发送的 API 请求数
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 2.0;
+
+// 第 n 次连续触发风控等待 BACKOFF_BASE * 2^n（最长 BACKOFF_MAX），再乘以 0.5~1.5 的随机系数
+const BACKOFF_BASE: Duration = Duration::from_secs(5);
+const BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+// 单个请求因风控最多尝试的次数
+const MAX_ATTEMPTS: u32 = 4;
+
+// 所有 API 请求共享的限速状态
+struct Limiter {
+    interval: Duration,              // 两次请求之间的最小间隔
+    next_slot: Option<Instant>,      // 下一个请求最早的发送时间
+    cooldown_until: Option<Instant>, // 风控冷却结束时间
+    level: u32,                      // 连续触发风控的次数，请求成功后清零
+}
+
+static LIMITER: Mutex<Limiter> = Mutex::new(Limiter {
+    interval: Duration::from_millis(500),
+    next_slot: None,
+    cooldown_until: None,
+    level: 0,
+});
+
+// 用于发送冷却事件和在冷却结束后恢复下载队列
+static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
+
+pub fn init(app_handle: &tauri::AppHandle, requests_per_second: f64) {
+    let _ = APP_HANDLE.set(app_handle.clone());
+    set_rate(requests_per_second);
+}
+
+// 设置每秒最多请求数，无效值使用默认值
+pub fn set_rate(requests_per_second: f64) {
+    let rate = if requests_per_second.is_finite() && requests_per_second > 0.0 {
+        requests_per_second
+    } else {
+        DEFAULT_REQUESTS_PER_SECOND
+    };
+    LIMITER.lock().unwrap().interval = Duration::from_secs_f64(1.0 / rate);
+    println!("API 请求速率: 每秒 {} 次", rate);
+}
+
+// 是否处于风控冷却中，冷却期间下载队列不启动新任务
+pub fn cooling_down() -> bool {
+    LIMITER.lock().unwrap().cooldown_until.is_some_and(|until| until > Instant::now())
+}
+
+fn is_rate_limited_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 412 | 429)
+}
+
+fn is_rate_limited_code(code: i32) -> bool {
+    matches!(code, -412 | -799)
+}
+
+// -352 既可能是风控也可能是 WBI 签名过期：同样进入冷却，但不在这里用旧签名重试，
+// 而是把响应交给调用方，由 get_signed 刷新密钥后重新签名请求（重试会等待冷却结束）
+fn is_risk_control_code(code: i32) -> bool {
+    code == -352
+}
+
+// 第 level 次连续触发风控的冷却时长
+fn backoff_delay(level: u32, jitter: f64) -> Duration {
+    BACKOFF_BASE
+        .saturating_mul(1 << level.min(6))
+        .min(BACKOFF_MAX)
+        .mul_f64(jitter)
+}
+
+impl Limiter {
+    // 触发风控：返回本次冷却时长并提升退避等级
+    fn escalate(&mut self) -> Duration {
+        let delay = backoff_delay(self.level, jitter());
+        self.level += 1;
+        delay
+    }
+
+    // 请求成功后退避等级清零
+    fn reset(&mut self) {
+        self.level = 0;
+    }
+}
+
+// 0.5 ~ 1.5 之间的随机系数，避免多个请求在冷却结束时同时重试
+fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    0.5 + (random % 1000) as f64 / 1000.0
+}
+
+fn emit_cooldown(cooldown: ApiCooldown) {
+    if let Some(app_handle) = APP_HANDLE.get() {
+        let _ = app_handle.emit(API_COOLDOWN_EVENT, cooldown);
+    }
+}
+
+// 按全局速率排队，等到可以发送的时间；冷却被延长时继续等待
+async fn acquire() {
+    loop {
+        let start = {
+            let mut limiter = LIMITER.lock().unwrap();
+            let now = Instant::now();
+            let start = [limiter.next_slot, limiter.cooldown_until]
+                .into_iter()
+                .flatten()
+                .fold(now, Instant::max);
+            limiter.next_slot = Some(start + limiter.interval);
+            start
+        };
+        tokio::time::sleep_until(start).await;
+
+        if !cooling_down() {
+            return;
+        }
+    }
+}
+
+// 触发风控：指数退避进入冷却，通知前端，冷却结束后恢复下载队列
+fn back_off(reason: String) {
+    let (delay, until) = {
+        let mut limiter = LIMITER.lock().unwrap();
+        let delay = limiter.escalate();
+
+        let until = limiter.cooldown_until.map_or(Instant::now() + delay, |current| current.max(Instant::now() + delay));
+        limiter.cooldown_until = Some(until);
+        (delay, until)
+    };
+
+    println!("⚠️ 触发风控 ({})，暂停 API 请求 {:.1} 秒", reason, delay.as_secs_f64());
+    emit_cooldown(ApiCooldown {
+        active: true,
+        remaining_secs: until.saturating_duration_since(Instant::now()).as_secs(),
+        reason,
+    });
+
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep_until(until).await;
+
+        // 冷却期间可能又被延长，只有最后一个到期的负责结束冷却
+        let ended = {
+            let mut limiter = LIMITER.lock().unwrap();
+            let expired = limiter.cooldown_until.is_some_and(|current| current <= Instant::now());
+            if expired {
+                limiter.cooldown_until = None;
+            }
+            expired
+        };
+        if !ended {
+            return;
+        }
+
+        println!("✅ 风控冷却结束，恢复 API 请求");
+        emit_cooldown(ApiCooldown { active: false, remaining_secs: 0, reason: String::new() });
+        if let Some(app_handle) = APP_HANDLE.get() {
+            app_handle.state::<crate::manager::DownloadManager>().schedule(app_handle);
+        }
+    });
+}
+
+// 只解析响应中的 code 和 message
+#[derive(Deserialize)]
+struct ApiEnvelope {
+    code: i32,
+    #[serde(default)]
+    message: String,
+}
+
+// 发送一个 API 请求并返回响应正文
+// 所有请求按全局速率排队；遇到 412/429 或 -412/-799 时退避冷却后重试，-352 只进入冷却不重试
+pub async fn send(request: reqwest::RequestBuilder) -> Result<String, DilidiliError> {
+    let mut attempt = 1;
+    loop {
+        let current = request.try_clone()
+            .ok_or_else(|| DilidiliError::InvalidInput("请求无法重试".to_string()))?;
+        acquire().await;
+
+        let response = current.send().await?;
+        let status = response.status();
+        let error = if is_rate_limited_status(status) {
+            DilidiliError::from_status(status)
+        } else if !status.is_success() {
+            return Err(DilidiliError::from_status(status));
+        } else {
+            let text = response.text().await?;
+            match serde_json::from_str::<ApiEnvelope>(&text) {
+                Ok(envelope) if is_rate_limited_code(envelope.code) => DilidiliError::Api {
+                    code: envelope.code,
+                    message: envelope.message,
+                },
+                Ok(envelope) if is_risk_control_code(envelope.code) => {
+                    back_off(format!("{} {}", envelope.code, envelope.message));
+                    return Ok(text);
+                }
+                _ => {
+                    LIMITER.lock().unwrap().reset();
+                    return Ok(text);
+                }
+            }
+        };
+
+        back_off(error.to_string());
+        if attempt >= MAX_ATTEMPTS {
+            return Err(error);
+        }
+        attempt += 1;
+        println!("冷却结束后第 {} 次尝试", attempt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_until_cap() {
+        let delays: Vec<u64> = (0..9).map(|level| backoff_delay(level, 1.0).as_secs()).collect();
+        assert_eq!(delays, [5, 10, 20, 40, 80, 160, 300, 300, 300]);
+        assert_eq!(backoff_delay(u32::MAX, 1.0), BACKOFF_MAX);
+        assert_eq!(backoff_delay(1, 0.5), Duration::from_secs(5));
+        assert_eq!(backoff_delay(6, 1.5), Duration::from_secs(450));
+    }
+
+    #[test]
+    fn jitter_stays_in_range() {
+        for _ in 0..1000 {
+            let factor = jitter();
+            assert!((0.5..1.5).contains(&factor), "{}", factor);
+        }
+    }
+
+    #[test]
+    fn level_resets_after_success() {
+        let mut limiter = Limiter { interval: Duration::ZERO, next_slot: None, cooldown_until: None, level: 0 };
+        let in_range = |delay: Duration, base: u64| {
+            delay >= Duration::from_secs(base).mul_f64(0.5) && delay < Duration::from_secs(base).mul_f64(1.5)
+        };
+
+        assert!(in_range(limiter.escalate(), 5));
+        assert!(in_range(limiter.escalate(), 10));
+        assert!(in_range(limiter.escalate(), 20));
+        assert_eq!(limiter.level, 3);
+
+        limiter.reset();
+        assert_eq!(limiter.level, 0);
+        assert!(in_range(limiter.escalate(), 5));
+    }
+
+    #[test]
+    fn classifies_risk_control_codes() {
+        assert!(is_rate_limited_code(-412) && is_rate_limited_code(-799));
+        assert!(!is_rate_limited_code(-352) && is_risk_control_code(-352));
+        assert!(crate::wbi::is_signature_rejected(-352));
+        assert!(!is_rate_limited_code(0) && !is_risk_control_code(0));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::api::BilibiliApi;
+use crate::client::BiliClient;
+use crate::error::DilidiliError;
+use crate::types::*;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Oaep, RsaPublicKey};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::Manager;
+
+// 生成 correspondPath 使用的公钥，来自 B站 网页端
+const CORRESPOND_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQDLgd2OAkcGVtoE3ThUREbio0Eg
+Uc/prcajMKXvkCKFCWhJYJcLkcM2DKKcSeFpD/j6Boy538YXnR6VhcuUJOhH2x71
+nzPjfdTcqMz7djHum0qSZA0AyCBDABUqCrfNgCiJ00Ra7GmRj+YCK1NJEuewlb40
+JNrRuoEUXpabUzGB8QIDAQAB
+-----END PUBLIC KEY-----";
+
+// 定时检查的间隔，是否真的刷新由 cookie/info 决定
+const CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+// 口令模式尚未解锁时的重试间隔，解锁后也会立即检查一次
+const LOCKED_RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+// 用公钥 RSA-OAEP(SHA-256) 加密 "refresh_<毫秒时间戳>"，结果转为小写十六进制
+pub fn correspond_path(timestamp: u64) -> Result<String, DilidiliError> {
+    let key = RsaPublicKey::from_public_key_pem(CORRESPOND_PUBLIC_KEY)
+        .map_err(|e| DilidiliError::Parse(format!("公钥解析失败: {}", e)))?;
+    let encrypted = key
+        .encrypt(&mut rsa::rand_core::OsRng, Oaep::new::<rsa::sha2::Sha256>(), format!("refresh_{}", timestamp).as_bytes())
+        .map_err(|e| DilidiliError::Parse(format!("生成 correspondPath 失败: {}", e)))?;
+    Ok(encrypted.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// correspond 页面中的 <div id="1-name">refresh_csrf</div>
+fn extract_refresh_csrf(html: &str) -> Option<String> {
+    let start = html.find("<div id=\"1-name\">")? + "<div id=\"1-name\">".len();
+    let end = html[start..].find("</div>")? + start;
+    let refresh_csrf = html[start..end].trim();
+    (!refresh_csrf.is_empty()).then(|| refresh_csrf.to_string())
+}
+
+fn cookie_value(cookies: &str, name: &str) -> Option<String> {
+    cookies.split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+// 按网页端流程刷新 cookie：cookie/info → correspond → cookie/refresh → confirm/refresh
+// 不需要刷新时返回 None，刷新成功返回新的 refresh_token，新的 cookie 已写入 api 的 cookie jar
+pub async fn refresh_cookies(api: &dyn BilibiliApi, refresh_token: &str) -> Result<Option<String>, DilidiliError> {
+    let csrf = cookie_value(&api.cookies(), "bili_jct")
+        .ok_or_else(|| DilidiliError::InvalidInput("cookie 中没有 bili_jct".to_string()))?;
+
+    let info: CookieInfoResponse = serde_json::from_str(&api.cookie_info(&csrf).await?)?;
+    let info = match info.data {
+        Some(data) if info.code == 0 => data,
+        _ => return Err(DilidiliError::Api { code: info.code, message: info.message }),
+    };
+    if !info.refresh {
+        return Ok(None);
+    }
+
+    println!("🔄 登录 cookie 需要刷新");
+    let html = api.correspond(&correspond_path(info.timestamp)?).await?;
+    let refresh_csrf = extract_refresh_csrf(&html)
+        .ok_or_else(|| DilidiliError::Parse("correspond 页面中没有 refresh_csrf".to_string()))?;
+
+    let refreshed: CookieRefreshResponse = serde_json::from_str(&api.cookie_refresh(&csrf, &refresh_csrf, refresh_token).await?)?;
+    let new_token = match refreshed.data {
+        Some(data) if refreshed.code == 0 => data.refresh_token,
+        _ => return Err(DilidiliError::Api { code: refreshed.code, message: refreshed.message }),
+    };
+
+    // 确认时使用新的 bili_jct 和旧的 refresh_token；确认失败不影响新 cookie 的使用
+    let new_csrf = cookie_value(&api.cookies(), "bili_jct").unwrap_or(csrf);
+    match api.confirm_refresh(&new_csrf, refresh_token).await.and_then(|text| Ok(serde_json::from_str::<ApiStatusResponse>(&text)?)) {
+        Ok(confirm) if confirm.code == 0 => {}
+        Ok(confirm) => println!("⚠️ 确认刷新失败: {} - {}", confirm.code, confirm.message),
+        Err(e) => println!("⚠️ 确认刷新失败: {}", e),
+    }
+
+    Ok(Some(new_token))
+}
+
+// 刷新一个账号的 cookie，返回更新后的登录数据；不需要刷新时返回 None
+async fn refresh_profile(client: &BiliClient, login_data: &StoredLoginData) -> Result<Option<StoredLoginData>, DilidiliError> {
+    let Some(refresh_token) = login_data.refresh_token.as_deref() else {
+        return Ok(None);
+    };
+    let Some(new_token) = refresh_cookies(client, refresh_token).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(StoredLoginData {
+        cookies: client.cookie_jar().header(),
+        refresh_token: Some(new_token),
+        login_time: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+        ..login_data.clone()
+    }))
+}
+
+// 检查所有账号，需要时刷新并写回；返回刷新了 cookie 的账号数
+// 当前账号通过共享的客户端刷新，新 cookie 立即生效；其他账号使用临时的客户端
+pub async fn refresh_login(app_handle: &tauri::AppHandle) -> Result<usize, DilidiliError> {
+    let (profiles, active) = crate::storage::load_profiles(app_handle)?;
+    let shared = app_handle.state::<BiliClient>().inner().clone();
+
+    let mut refreshed = 0;
+    for profile in &profiles {
+        if profile.login_data.refresh_token.is_none() {
+            println!("账号 {} 没有 refresh_token，跳过 cookie 刷新", profile.name);
+            continue;
+        }
+
+        let client = if active.as_deref() == Some(profile.id.as_str()) {
+            shared.clone()
+        } else {
+            shared.with_cookies(&profile.login_data.cookies)?
+        };
+        match refresh_profile(&client, &profile.login_data).await {
+            Ok(Some(login_data)) => {
+                crate::storage::update_profile_login_data(app_handle, &profile.id, &login_data)?;
+                println!("✅ 账号 {} 的登录 cookie 已刷新", profile.name);
+                refreshed += 1;
+            }
+            Ok(None) => {}
+            Err(e) => println!("⚠️ 刷新账号 {} 的登录 cookie 失败: {}", profile.name, e),
+        }
+    }
+    Ok(refreshed)
+}
+
+// 启动时立即检查一次，之后每隔 CHECK_INTERVAL 检查；登录数据未解锁时隔 LOCKED_RETRY_INTERVAL 重试
+pub fn spawn_scheduler(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let interval = match refresh_login(&app_handle).await {
+                Ok(_) => CHECK_INTERVAL,
+                Err(DilidiliError::CredentialsLocked) => {
+                    println!("登录数据尚未解锁，稍后再检查 cookie 刷新");
+                    LOCKED_RETRY_INTERVAL
+                }
+                Err(e) => {
+                    println!("⚠️ 刷新登录 cookie 失败: {}", e);
+                    CHECK_INTERVAL
+                }
+            };
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+// 在后台检查一次（如口令解锁后），不等待结果
+pub fn spawn_refresh(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = refresh_login(&app_handle).await {
+            println!("⚠️ 刷新登录 cookie 失败: {}", e);
+        }
+    });
+}
+
+// 立即检查并刷新所有账号的登录 cookie，返回刷新的账号数
+#[tauri::command]
+pub async fn refresh_login_cookies(app_handle: tauri::AppHandle) -> Result<usize, DilidiliError> {
+    refresh_login(&app_handle).await
+}
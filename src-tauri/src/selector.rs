@@ -0,0 +1,281 @@
+use crate::error::DilidiliError;
+use crate::types::*;
+use std::cmp::Reverse;
+
+// 按 SelectionRules 从可用的 DASH 流中自动挑选视频流和音频流
+pub struct StreamSelector<'a> {
+    rules: &'a SelectionRules,
+}
+
+// 流的短边分辨率，竖屏视频按宽度计算；缺失时视为满足限制
+fn short_side(stream: &VideoStream) -> Option<u32> {
+    match (stream.width, stream.height) {
+        (Some(w), Some(h)) => Some(w.min(h)),
+        (_, h) => h,
+    }
+}
+
+// 预估流大小：优先使用 filesize，否则按 bandwidth × 时长估算
+fn estimated_size(filesize: Option<u64>, bandwidth: Option<u32>, duration: u32) -> Option<u64> {
+    filesize.or_else(|| bandwidth.map(|b| b as u64 * duration as u64 / 8))
+}
+
+impl<'a> StreamSelector<'a> {
+    pub fn new(rules: &'a SelectionRules) -> Self {
+        Self { rules }
+    }
+
+    // 编码在偏好列表中的位置，越小越优先
+    fn codec_rank(&self, codec: VideoCodec) -> usize {
+        self.rules.codec_order.iter()
+            .position(|c| *c == codec)
+            .unwrap_or(self.rules.codec_order.len())
+    }
+
+    fn video_size(stream: &VideoStream, duration: u32) -> Option<u64> {
+        estimated_size(stream.filesize, stream.bandwidth, duration)
+    }
+
+    fn audio_size(stream: &AudioStream, duration: u32) -> Option<u64> {
+        estimated_size(stream.filesize, stream.bandwidth, duration)
+    }
+
+    // 是否满足分辨率和大小限制，budget 为留给视频流的字节数
+    fn fits(&self, stream: &VideoStream, budget: Option<u64>, duration: u32) -> bool {
+        let height_ok = match (self.rules.max_height, short_side(stream)) {
+            (Some(max), Some(h)) => h <= max,
+            _ => true,
+        };
+        let size_ok = match (budget, Self::video_size(stream, duration)) {
+            (Some(budget), Some(size)) => size <= budget,
+            _ => true,
+        };
+        height_ok && size_ok
+    }
+
+    // 按偏好挑选：分辨率优先，其次帧率和清晰度代码，再按编码偏好，最后取码率高的
+    fn preferred<'s>(&self, candidates: impl Iterator<Item = &'s VideoStream>) -> Option<&'s VideoStream> {
+        candidates.max_by_key(|stream| {
+            let fps = stream.frame_rate.unwrap_or_default().round() as i64;
+            (
+                short_side(stream).unwrap_or_default(),
+                if self.rules.prefer_high_fps { fps } else { -fps },
+                stream.quality,
+                Reverse(self.codec_rank(stream.codec)),
+                stream.bandwidth.unwrap_or_default(),
+            )
+        })
+    }
+
+    // 音频取码率最高的一路，未开启 premium_audio 时不考虑杜比和无损音轨
+    pub fn select_audio<'s>(&self, streams: &'s [AudioStream]) -> Option<&'s AudioStream> {
+        let allowed = |stream: &&AudioStream| self.rules.premium_audio || !stream.codec.is_premium();
+        streams.iter()
+            .filter(allowed)
+            .max_by_key(|stream| (stream.bandwidth.unwrap_or_default(), stream.quality))
+            .or_else(|| streams.first())
+    }
+
+    pub fn select_video<'s>(&self, streams: &'s [VideoStream], audio: Option<&AudioStream>, duration: u32) -> Result<&'s VideoStream, DilidiliError> {
+        if streams.is_empty() {
+            return Err(DilidiliError::InvalidInput("没有可用的视频流".to_string()));
+        }
+
+        let audio_size = audio.and_then(|a| Self::audio_size(a, duration)).unwrap_or(0);
+        let budget = self.rules.max_size.map(|max| max.saturating_sub(audio_size));
+
+        if let Some(stream) = self.preferred(streams.iter().filter(|s| self.fits(s, budget, duration))) {
+            return Ok(stream);
+        }
+
+        println!("⚠️ 没有满足选流规则的视频流，按 {:?} 处理", self.rules.fallback);
+        match self.rules.fallback {
+            SelectionFallback::Lowest => streams.iter()
+                .min_by_key(|s| (Self::video_size(s, duration).unwrap_or(u64::MAX), s.quality))
+                .ok_or_else(|| DilidiliError::InvalidInput("没有可用的视频流".to_string())),
+            SelectionFallback::Best => self.preferred(streams.iter())
+                .ok_or_else(|| DilidiliError::InvalidInput("没有可用的视频流".to_string())),
+            SelectionFallback::Fail => Err(DilidiliError::InvalidInput("没有满足选流规则的视频流".to_string())),
+        }
+    }
+
+    // 同时选出视频流和音频流，durl 流自带音频，没有单独的音频流
+    pub fn select(&self, streams: &SimplifiedPlayUrlData, duration: u32) -> Result<(VideoStream, Option<AudioStream>), DilidiliError> {
+        let audio = self.select_audio(&streams.audio_streams);
+        let video = self.select_video(&streams.video_streams, audio, duration)?;
+
+        println!("自动选流: {} + {}", video.label, audio.map(|a| a.description.as_str()).unwrap_or("无独立音频"));
+        Ok((video.clone(), audio.cloned()))
+    }
+}
+
+// 按设置中的规则为前端预选流
+#[tauri::command]
+pub async fn select_streams(
+    app_handle: tauri::AppHandle,
+    streams: SimplifiedPlayUrlData,
+    duration: u32
+) -> Result<(VideoStream, Option<AudioStream>), DilidiliError> {
+    let settings = crate::settings::load_settings(&app_handle)?;
+    StreamSelector::new(&settings.selection).select(&streams, duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DURATION: u32 = 100;
+
+    fn video(quality: u32, height: u32, codec: VideoCodec, fps: f64, bandwidth: u32) -> VideoStream {
+        VideoStream {
+            quality,
+            format: "mp4".to_string(),
+            description: String::new(),
+            label: format!("{} {:?} {}", quality, codec, fps),
+            codec,
+            codecs: None,
+            codecid: None,
+            width: Some(height * 16 / 9),
+            height: Some(height),
+            frame_rate: Some(fps),
+            bandwidth: Some(bandwidth),
+            mime_type: None,
+            url: None,
+            backup_urls: Vec::new(),
+            filesize: None,
+            segments: Vec::new(),
+        }
+    }
+
+    fn audio(quality: u32, codec: AudioCodec, bandwidth: u32) -> AudioStream {
+        AudioStream {
+            quality,
+            format: "m4a".to_string(),
+            description: String::new(),
+            codec,
+            codecs: None,
+            codecid: None,
+            bandwidth: Some(bandwidth),
+            mime_type: None,
+            url: None,
+            backup_urls: Vec::new(),
+            filesize: None,
+        }
+    }
+
+    // 按码率估算（时长 100 秒）：120 约 200MB，116 AVC 75MB，116 HEVC 50MB，80 37.5MB，64 18.75MB，32 10MB
+    fn videos() -> Vec<VideoStream> {
+        vec![
+            video(120, 2160, VideoCodec::Hevc, 30.0, 16_000_000),
+            video(116, 1080, VideoCodec::Avc, 60.0, 6_000_000),
+            video(116, 1080, VideoCodec::Hevc, 60.0, 4_000_000),
+            video(80, 1080, VideoCodec::Avc, 30.0, 3_000_000),
+            video(64, 720, VideoCodec::Avc, 30.0, 1_500_000),
+            video(32, 480, VideoCodec::Avc, 30.0, 800_000),
+        ]
+    }
+
+    fn pick(rules: &SelectionRules, streams: &[VideoStream]) -> Result<(u32, VideoCodec), DilidiliError> {
+        StreamSelector::new(rules)
+            .select_video(streams, None, DURATION)
+            .map(|stream| (stream.quality, stream.codec))
+    }
+
+    #[test]
+    fn applies_resolution_fps_and_codec_rules() {
+        let streams = videos();
+        let cases = [
+            (SelectionRules::default(), (120, VideoCodec::Hevc)),
+            (SelectionRules { max_height: Some(1080), ..Default::default() }, (116, VideoCodec::Avc)),
+            (
+                SelectionRules { max_height: Some(1080), codec_order: vec![VideoCodec::Hevc, VideoCodec::Avc], ..Default::default() },
+                (116, VideoCodec::Hevc),
+            ),
+            // 未列出的编码排在最后
+            (
+                SelectionRules { max_height: Some(1080), codec_order: vec![VideoCodec::Av1], ..Default::default() },
+                (116, VideoCodec::Avc),
+            ),
+            (SelectionRules { max_height: Some(1080), prefer_high_fps: false, ..Default::default() }, (80, VideoCodec::Avc)),
+            (SelectionRules { max_height: Some(720), ..Default::default() }, (64, VideoCodec::Avc)),
+        ];
+        for (rules, expected) in cases {
+            assert_eq!(pick(&rules, &streams).unwrap(), expected, "{:?}", rules);
+        }
+    }
+
+    #[test]
+    fn portrait_video_uses_short_side() {
+        let mut portrait = video(116, 1080, VideoCodec::Avc, 30.0, 3_000_000);
+        portrait.width = Some(1080);
+        portrait.height = Some(1920);
+        let rules = SelectionRules { max_height: Some(1080), ..Default::default() };
+        assert_eq!(pick(&rules, &[portrait]).unwrap(), (116, VideoCodec::Avc));
+    }
+
+    #[test]
+    fn size_cap_includes_audio() {
+        let streams = videos();
+        let aac = audio(30280, AudioCodec::Aac, 320_000); // 4MB
+        let rules = SelectionRules { max_size: Some(40_000_000), ..Default::default() };
+        let selector = StreamSelector::new(&rules);
+
+        // 不含音频时 80（37.5MB）满足上限；加上 4MB 音频后只剩 64（18.75MB）
+        assert_eq!(selector.select_video(&streams, None, DURATION).unwrap().quality, 80);
+        assert_eq!(selector.select_video(&streams, Some(&aac), DURATION).unwrap().quality, 64);
+
+        // 有 filesize 时优先于码率估算
+        let mut streams = streams;
+        streams[0].filesize = Some(30_000_000);
+        assert_eq!(selector.select_video(&streams, Some(&aac), DURATION).unwrap().quality, 120);
+    }
+
+    #[test]
+    fn falls_back_when_nothing_fits() {
+        let streams = videos();
+        let rules = |fallback| SelectionRules { max_height: Some(360), fallback, ..Default::default() };
+
+        assert_eq!(pick(&rules(SelectionFallback::Lowest), &streams).unwrap(), (32, VideoCodec::Avc));
+        assert_eq!(pick(&rules(SelectionFallback::Best), &streams).unwrap(), (120, VideoCodec::Hevc));
+        assert_eq!(pick(&rules(SelectionFallback::Fail), &streams).unwrap_err().code(), "invalid_input");
+
+        let size_rules = SelectionRules { max_size: Some(1), fallback: SelectionFallback::Lowest, ..Default::default() };
+        assert_eq!(pick(&size_rules, &streams).unwrap(), (32, VideoCodec::Avc));
+        assert!(pick(&SelectionRules::default(), &[]).is_err());
+    }
+
+    #[test]
+    fn premium_audio_is_opt_in() {
+        let streams = [
+            audio(30216, AudioCodec::Aac, 64_000),
+            audio(30280, AudioCodec::Aac, 320_000),
+            audio(30250, AudioCodec::Eac3, 768_000),
+            audio(30251, AudioCodec::Flac, 1_000_000),
+        ];
+        let quality = |premium_audio, streams: &[AudioStream]| {
+            let rules = SelectionRules { premium_audio, ..Default::default() };
+            StreamSelector::new(&rules).select_audio(streams).map(|a| a.quality)
+        };
+
+        assert_eq!(quality(false, &streams), Some(30280));
+        assert_eq!(quality(true, &streams), Some(30251));
+        // 只有杜比/无损音轨时仍然返回一路
+        assert_eq!(quality(false, &streams[2..]), Some(30250));
+        assert_eq!(quality(false, &[]), None);
+    }
+
+    #[test]
+    fn selects_video_and_audio_together() {
+        let rules = SelectionRules { max_height: Some(1080), ..Default::default() };
+        let streams = SimplifiedPlayUrlData {
+            video_streams: videos(),
+            audio_streams: vec![audio(30280, AudioCodec::Aac, 320_000)],
+        };
+        let (video, audio) = StreamSelector::new(&rules).select(&streams, DURATION).unwrap();
+        assert_eq!((video.quality, audio.map(|a| a.quality)), (116, Some(30280)));
+
+        // durl 流没有单独的音频
+        let durl = SimplifiedPlayUrlData { video_streams: videos(), audio_streams: Vec::new() };
+        assert!(StreamSelector::new(&rules).select(&durl, DURATION).unwrap().1.is_none());
+    }
+}
@@ -0,0 +1,44 @@
+use crate::error::DilidiliError;
+use crate::types::*;
+
+// 设置持久化文件
+const SETTINGS_STORE: &str = "settings.json";
+
+// 读取设置，未保存过时返回默认值
+pub fn load_settings(app_handle: &tauri::AppHandle) -> Result<AppSettings, DilidiliError> {
+    use tauri_plugin_store::StoreExt;
+
+    let store = app_handle.store(SETTINGS_STORE)?;
+
+    match store.get("settings") {
+        Some(value) => serde_json::from_value(value).map_err(|e| DilidiliError::Parse(format!("设置文件格式错误: {}", e))),
+        None => Ok(AppSettings::default()),
+    }
+}
+
+// 获取设置
+#[tauri::command]
+pub async fn get_settings(app_handle: tauri::AppHandle) -> Result<AppSettings, DilidiliError> {
+    load_settings(&app_handle)
+}
+
+// 保存设置
+#[tauri::command]
+pub async fn save_settings(
+    app_handle: tauri::AppHandle,
+    client: tauri::State<'_, crate::client::BiliClient>,
+    settings: AppSettings
+) -> Result<(), DilidiliError> {
+    use tauri_plugin_store::StoreExt;
+
+    // 代理设置无效时不保存
+    client.set_proxy(&settings.proxy)?;
+
+    let store = app_handle.store(SETTINGS_STORE)?;
+    store.set("settings", serde_json::to_value(&settings)?);
+    store.save()?;
+    crate::ratelimit::set_rate(settings.requests_per_second);
+
+    println!("设置已保存");
+    Ok(())
+}
@@ -1,40 +1,218 @@
+use crate::client::BiliClient;
+use crate::error::DilidiliError;
 use crate::types::*;
+use crate::vault::{self, Vault};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Manager;
 
-// 保存登录数据
-#[tauri::command]
-pub async fn save_login_data(app_handle: tauri::AppHandle, login_data: StoredLoginData) -> Result<(), String> {
-    use tauri_plugin_store::StoreExt;
-    
-    let store = app_handle.store("login.json").map_err(|e| e.to_string())?;
-    store.set("login_data", serde_json::to_value(&login_data).map_err(|e| e.to_string())?);
-    store.save().map_err(|e| e.to_string())?;
-    
-    Ok(())
+// 登录数据文件：
+//   profiles       账号列表，cookie 和 refresh_token 加密保存
+//   active_profile 当前账号的 id
+//   login_data     旧版本的单账号数据，读取时迁移为一个账号
+const LOGIN_STORE: &str = "login.json";
+
+// 加密 cookie 和 refresh_token
+fn seal_login_data(app_handle: &tauri::AppHandle, login_data: &StoredLoginData) -> Result<StoredLoginData, DilidiliError> {
+    let key = app_handle.state::<Vault>().key(app_handle)?;
+    let mut sealed = login_data.clone();
+    sealed.cookies = vault::seal(&key, "cookies", &login_data.cookies)?;
+    sealed.refresh_token = login_data.refresh_token.as_deref()
+        .map(|token| vault::seal(&key, "refresh_token", token))
+        .transpose()?;
+    Ok(sealed)
 }
 
-// 加载登录数据
-#[tauri::command]
-pub async fn load_login_data(app_handle: tauri::AppHandle) -> Result<Option<StoredLoginData>, String> {
+// 解密 cookie 和 refresh_token，旧版本明文保存的原样返回
+fn open_login_data(app_handle: &tauri::AppHandle, mut login_data: StoredLoginData) -> Result<StoredLoginData, DilidiliError> {
+    if !vault::is_sealed(&login_data.cookies) {
+        return Ok(login_data);
+    }
+
+    let key = app_handle.state::<Vault>().key(app_handle)?;
+    login_data.cookies = vault::open(&key, "cookies", &login_data.cookies)?;
+    login_data.refresh_token = login_data.refresh_token
+        .map(|token| vault::open(&key, "refresh_token", &token))
+        .transpose()?;
+    Ok(login_data)
+}
+
+// 读取全部账号（已解密）和当前账号的 id
+pub fn load_profiles(app_handle: &tauri::AppHandle) -> Result<(Vec<AccountProfile>, Option<String>), DilidiliError> {
     use tauri_plugin_store::StoreExt;
-    
-    let store = app_handle.store("login.json").map_err(|e| e.to_string())?;
-    
-    if let Some(value) = store.get("login_data") {
-        let login_data: StoredLoginData = serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
-        Ok(Some(login_data))
-    } else {
-        Ok(None)
+
+    let store = app_handle.store(LOGIN_STORE)?;
+    let profiles: Vec<AccountProfile> = match store.get("profiles") {
+        Some(value) => serde_json::from_value(value)?,
+        None => Vec::new(),
+    };
+    let mut active = store.get("active_profile").and_then(|value| value.as_str().map(str::to_string));
+
+    // 有加密数据时先取得密钥，未解锁或本机密钥校验失败时整体报错，不改动保存的数据
+    let legacy = store.get("login_data");
+    let sealed = profiles.iter().any(|profile| vault::is_sealed(&profile.login_data.cookies))
+        || legacy.as_ref().and_then(|value| value["cookies"].as_str()).is_some_and(vault::is_sealed);
+    if sealed {
+        app_handle.state::<Vault>().key(app_handle)?;
+    }
+
+    // 旧版本的明文或单账号数据，读取后立即按新格式加密保存；
+    // 密钥正确但内容已损坏、无法解密的账号直接丢弃，需要重新登录
+    let mut migrate = false;
+    let mut opened = Vec::with_capacity(profiles.len());
+    for mut profile in profiles {
+        migrate |= !vault::is_sealed(&profile.login_data.cookies);
+        match open_login_data(app_handle, profile.login_data.clone()) {
+            Ok(login_data) => {
+                profile.login_data = login_data;
+                opened.push(profile);
+            }
+            Err(DilidiliError::CredentialsLocked) => return Err(DilidiliError::CredentialsLocked),
+            Err(e) => {
+                println!("⚠️ 账号 {} 的登录数据无法解密，已移除，需要重新登录: {}", profile.name, e);
+                if active.as_deref() == Some(profile.id.as_str()) {
+                    active = None;
+                }
+                migrate = true;
+            }
+        }
+    }
+    let mut profiles = opened;
+    if let Some(value) = legacy {
+        match open_login_data(app_handle, serde_json::from_value(value)?) {
+            Ok(login_data) => {
+                let profile = new_profile(&profiles, login_data);
+                println!("迁移旧版本的登录数据为账号: {}", profile.name);
+                active = Some(profile.id.clone());
+                profiles.push(profile);
+            }
+            Err(DilidiliError::CredentialsLocked) => return Err(DilidiliError::CredentialsLocked),
+            Err(e) => println!("⚠️ 旧版本的登录数据无法解密，已移除: {}", e),
+        }
+        migrate = true;
+    }
+    if migrate {
+        if let Err(e) = save_profiles(app_handle, &profiles, active.as_deref()) {
+            println!("⚠️ 迁移登录数据失败: {}", e);
+        }
     }
+
+    Ok((profiles, active))
 }
 
-// 清除登录数据
-#[tauri::command]
-pub async fn clear_login_data(app_handle: tauri::AppHandle) -> Result<(), String> {
+// 保存全部账号和当前账号的 id
+pub fn save_profiles(app_handle: &tauri::AppHandle, profiles: &[AccountProfile], active: Option<&str>) -> Result<(), DilidiliError> {
     use tauri_plugin_store::StoreExt;
-    
-    let store = app_handle.store("login.json").map_err(|e| e.to_string())?;
+
+    let sealed = profiles.iter()
+        .map(|profile| Ok(AccountProfile { login_data: seal_login_data(app_handle, &profile.login_data)?, ..profile.clone() }))
+        .collect::<Result<Vec<_>, DilidiliError>>()?;
+
+    let store = app_handle.store(LOGIN_STORE)?;
+    store.set("profiles", serde_json::to_value(&sealed)?);
+    match active {
+        Some(id) => store.set("active_profile", id),
+        None => {
+            store.delete("active_profile");
+        }
+    }
     store.delete("login_data");
-    store.save().map_err(|e| e.to_string())?;
+    store.save()?;
+
+    Ok(())
+}
+
+// 新账号，id 取当前毫秒数，名称默认为B站用户名
+fn new_profile(profiles: &[AccountProfile], login_data: StoredLoginData) -> AccountProfile {
+    let mut id = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+    while profiles.iter().any(|profile| profile.id == id.to_string()) {
+        id += 1;
+    }
+    let name = login_data.user_profile.as_ref()
+        .map(|user| user.name.clone())
+        .unwrap_or_else(|| format!("账号 {}", profiles.len() + 1));
+    AccountProfile { id: id.to_string(), name, login_data }
+}
+
+// 扫码登录成功：已有同一 mid 的账号时更新其登录数据，否则新建账号；设为当前账号并返回其 id
+pub fn add_login_profile(app_handle: &tauri::AppHandle, login_data: &StoredLoginData) -> Result<String, DilidiliError> {
+    let (mut profiles, _) = load_profiles(app_handle)?;
+
+    let mid = login_data.user_profile.as_ref().map(|user| user.mid);
+    let existing = profiles.iter_mut()
+        .find(|profile| mid.is_some() && profile.login_data.user_profile.as_ref().map(|user| user.mid) == mid);
+    let id = match existing {
+        Some(profile) => {
+            profile.login_data = login_data.clone();
+            profile.id.clone()
+        }
+        None => {
+            let profile = new_profile(&profiles, login_data.clone());
+            let id = profile.id.clone();
+            profiles.push(profile);
+            id
+        }
+    };
+
+    save_profiles(app_handle, &profiles, Some(&id))?;
+    Ok(id)
+}
+
+// 更新指定账号的登录数据
+pub fn update_profile_login_data(app_handle: &tauri::AppHandle, profile_id: &str, login_data: &StoredLoginData) -> Result<(), DilidiliError> {
+    let (mut profiles, active) = load_profiles(app_handle)?;
+    let profile = profiles.iter_mut()
+        .find(|profile| profile.id == profile_id)
+        .ok_or_else(|| DilidiliError::InvalidInput(format!("账号不存在: {}", profile_id)))?;
+    profile.login_data = login_data.clone();
+    save_profiles(app_handle, &profiles, active.as_deref())
+}
+
+// 服务器通过 Set-Cookie 更新 cookie 后写回账号，profile_id 为空时写入当时的当前账号
+pub fn persist_cookie_changes(app_handle: &tauri::AppHandle, client: &BiliClient, profile_id: Option<String>) {
+    let app_handle = app_handle.clone();
+    client.cookie_jar().on_change(move |cookies| {
+        if let Err(e) = write_profile_cookies(&app_handle, profile_id.as_deref(), cookies) {
+            println!("⚠️ 保存更新的 cookie 失败: {}", e);
+        }
+    });
+}
+
+fn write_profile_cookies(app_handle: &tauri::AppHandle, profile_id: Option<&str>, cookies: &str) -> Result<(), DilidiliError> {
+    let (mut profiles, active) = load_profiles(app_handle)?;
+    // 未登录时没有需要保存的账号
+    let Some(id) = profile_id.or(active.as_deref()) else {
+        return Ok(());
+    };
+    let Some(profile) = profiles.iter_mut().find(|profile| profile.id == id) else {
+        return Ok(());
+    };
+    profile.login_data.cookies = cookies.to_string();
+    save_profiles(app_handle, &profiles, active.as_deref())
+}
+
+// 加载当前账号的登录信息（不含 cookie 和 refresh_token）
+#[tauri::command]
+pub async fn load_login_data(app_handle: tauri::AppHandle) -> Result<Option<LoginInfo>, DilidiliError> {
+    Ok(read_login_data(&app_handle)?.as_ref().map(LoginInfo::from))
+}
+
+// 读取当前账号的登录数据，启动时用于初始化 BiliClient 的 cookie
+pub fn read_login_data(app_handle: &tauri::AppHandle) -> Result<Option<StoredLoginData>, DilidiliError> {
+    let (profiles, active) = load_profiles(app_handle)?;
+    Ok(active
+        .and_then(|id| profiles.into_iter().find(|profile| profile.id == id))
+        .map(|profile| profile.login_data))
+}
+
+// 退出登录：删除当前账号，其他账号保留
+#[tauri::command]
+pub async fn clear_login_data(app_handle: tauri::AppHandle, client: tauri::State<'_, BiliClient>) -> Result<(), DilidiliError> {
+    let (mut profiles, active) = load_profiles(&app_handle)?;
+    if let Some(active) = active {
+        profiles.retain(|profile| profile.id != active);
+    }
+    save_profiles(&app_handle, &profiles, None)?;
+    client.cookie_jar().clear();
     
     Ok(())
-} 
\ No newline at end of file
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 // API 返回的二维码数据结构
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct QrCodeData {
     pub url: String,
     pub qrcode_key: String,
@@ -28,15 +28,51 @@ pub struct PollResponse {
     pub data: PollData,
 }
 
-// 登录成功后返回给前端的结构
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LoginSuccessData {
-    pub poll_data: PollData,
-    pub cookies: String,
+// 二维码纠错等级，等级越高越耐污损，图案也越密
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QrEcLevel {
+    L,
+    #[default]
+    M,
+    Q,
+    H,
+}
+
+// 二维码渲染选项
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct QrRenderOptions {
+    pub size: u32, // 图片边长（像素），包含四周留白
+    pub ec_level: QrEcLevel,
+}
+
+impl Default for QrRenderOptions {
+    fn default() -> Self {
+        Self { size: 200, ec_level: QrEcLevel::M }
+    }
+}
+
+// 渲染好的二维码图片，可以直接作为 <img> 的 src
+#[derive(Debug, Serialize, Clone)]
+pub struct QrImage {
+    pub svg: String, // data:image/svg+xml;base64,...
+    pub png: String, // data:image/png;base64,...
+}
+
+// 扫码登录状态事件（login-status）
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum LoginStatus {
+    WaitingScan { qrcode: QrCodeData, image: QrImage },     // 新二维码，等待扫码
+    Scanned,                                                // 已扫码，等待在手机上确认
+    Expired { regenerating: bool },                         // 二维码过期，regenerating 时随后推送新二维码
+    Confirmed { login_info: LoginInfo },                    // 登录成功，登录数据已保存
+    Failed { error: crate::error::ErrorInfo },
 }
 
 // 视频信息相关结构体
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VideoPage {
     pub cid: u64,
     pub page: u32,
@@ -44,7 +80,7 @@ pub struct VideoPage {
     pub duration: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VideoData {
     pub bvid: String,
     pub aid: u64,
@@ -55,9 +91,11 @@ pub struct VideoData {
     pub owner_info: VideoOwner,
     pub duration: u32,
     pub pages: Vec<VideoPage>,
+    #[serde(default)]
+    pub pubdate: u64, // 发布时间（unix 秒）
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VideoOwner {
     pub name: String,
     pub face: Option<String>,
@@ -70,6 +108,13 @@ impl VideoData {
     }
 }
 
+// 同一视频的 av 号和 BV 号
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct VideoIds {
+    pub aid: u64,
+    pub bvid: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VideoInfoResponse {
     pub code: i32,
@@ -109,6 +154,21 @@ pub struct DashAudio {
     pub codecid: Option<u32>,
 }
 
+// 杜比音效（大会员），audio 为 E-AC-3 音轨
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DashDolby {
+    #[serde(rename = "type")]
+    pub dolby_type: u32,
+    pub audio: Option<Vec<DashAudio>>,
+}
+
+// Hi-Res 无损音轨（大会员）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DashFlac {
+    pub display: bool,
+    pub audio: Option<DashAudio>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DashData {
     pub duration: u32,
@@ -116,6 +176,20 @@ pub struct DashData {
     pub min_buffer_time: f32,
     pub video: Vec<DashVideo>,
     pub audio: Vec<DashAudio>,
+    #[serde(default)]
+    pub dolby: Option<DashDolby>,
+    #[serde(default)]
+    pub flac: Option<DashFlac>,
+}
+
+// 旧版 durl 响应中的一个分段（FLV/MP4，音视频已封装在一起）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DurlSegment {
+    pub order: u32,
+    pub length: u64, // 毫秒
+    pub size: u64,
+    pub url: String,
+    pub backup_url: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -132,7 +206,11 @@ pub struct PlayUrlData {
     pub video_codecid: u32,
     pub seek_param: String,
     pub seek_type: String,
-    pub dash: DashData,
+    // 老视频、部分番剧和 fnval 回退时只返回 durl，没有 dash
+    #[serde(default)]
+    pub dash: Option<DashData>,
+    #[serde(default)]
+    pub durl: Option<Vec<DurlSegment>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -142,21 +220,152 @@ pub struct PlayUrlResponse {
     pub data: Option<PlayUrlData>,
 }
 
-// 为了保持前端兼容性，创建转换结构
-#[derive(Debug, Serialize, Deserialize)]
+// 视频编码
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum VideoCodec {
+    Avc,
+    Hevc,
+    Av1,
+    Unknown,
+}
+
+impl VideoCodec {
+    // codecid: 7=AVC, 12=HEVC, 13=AV1；缺失时按 codecs 字符串判断
+    pub fn detect(codecid: Option<u32>, codecs: Option<&str>) -> Self {
+        match codecid {
+            Some(7) => return VideoCodec::Avc,
+            Some(12) => return VideoCodec::Hevc,
+            Some(13) => return VideoCodec::Av1,
+            _ => {}
+        }
+        match codecs.unwrap_or_default() {
+            c if c.starts_with("avc") => VideoCodec::Avc,
+            c if c.starts_with("hev") || c.starts_with("hvc") => VideoCodec::Hevc,
+            c if c.starts_with("av01") => VideoCodec::Av1,
+            _ => VideoCodec::Unknown,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VideoCodec::Avc => "AVC",
+            VideoCodec::Hevc => "HEVC",
+            VideoCodec::Av1 => "AV1",
+            VideoCodec::Unknown => "未知编码",
+        }
+    }
+}
+
+// 音频编码
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum AudioCodec {
+    Aac,
+    Eac3,
+    Flac,
+    Unknown,
+}
+
+impl AudioCodec {
+    // 音质代码 30250=杜比全景声，30251=Hi-Res 无损；其余按 codecs 字符串判断
+    pub fn detect(quality: u32, codecs: Option<&str>) -> Self {
+        match quality {
+            30250 => return AudioCodec::Eac3,
+            30251 => return AudioCodec::Flac,
+            _ => {}
+        }
+        match codecs.unwrap_or_default().to_ascii_lowercase().as_str() {
+            c if c.starts_with("mp4a") => AudioCodec::Aac,
+            c if c.starts_with("ec-3") || c.starts_with("ac-3") => AudioCodec::Eac3,
+            c if c.starts_with("flac") => AudioCodec::Flac,
+            _ => AudioCodec::Unknown,
+        }
+    }
+
+    // 杜比和无损音轨需要原样封装，不能转码
+    pub fn is_premium(&self) -> bool {
+        matches!(self, AudioCodec::Eac3 | AudioCodec::Flac)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioCodec::Aac => "AAC",
+            AudioCodec::Eac3 => "E-AC-3",
+            AudioCodec::Flac => "FLAC",
+            AudioCodec::Unknown => "未知编码",
+        }
+    }
+
+    // 仅下载音频时输出文件的扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioCodec::Eac3 => "eac3",
+            AudioCodec::Flac => "flac",
+            _ => "m4a",
+        }
+    }
+}
+
+// 前端使用的视频流，每个 DASH video 条目对应一个
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VideoStream {
-    pub quality: u32,
-    pub format: String,
-    pub description: String,
+    pub quality: u32,             // 清晰度代码 qn（DashVideo.id）
+    pub format: String,           // 容器格式，来自 mime_type
+    pub description: String,      // 清晰度名称，如 "1080P 60帧"
+    #[serde(default)]
+    pub label: String,            // 完整标签，如 "1080P60 HEVC"
+    #[serde(default = "default_codec")]
+    pub codec: VideoCodec,
+    pub codecs: Option<String>,
+    pub codecid: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub bandwidth: Option<u32>,   // bit/s
+    pub mime_type: Option<String>,
     pub url: Option<String>,
+    #[serde(default)]
+    pub backup_urls: Vec<String>,
     pub filesize: Option<u64>,
+    #[serde(default)]
+    pub segments: Vec<StreamSegment>, // durl 分段，非空时音视频已封装在一起，需逐段下载后拼接
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// durl 流的一个分段
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StreamSegment {
+    pub url: String,
+    #[serde(default)]
+    pub backup_urls: Vec<String>,
+    pub size: Option<u64>,
+    pub length: Option<u64>, // 毫秒
+}
+
+fn default_codec() -> VideoCodec {
+    VideoCodec::Unknown
+}
+
+fn default_audio_codec() -> AudioCodec {
+    AudioCodec::Aac
+}
+
+// 前端使用的音频流，每个 DASH audio 条目对应一个
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AudioStream {
-    pub quality: u32,
+    pub quality: u32,             // 音质代码（DashAudio.id）
     pub format: String,
+    #[serde(default)]
+    pub description: String,      // 音质名称，如 "192K"
+    #[serde(default = "default_audio_codec")]
+    pub codec: AudioCodec,
+    pub codecs: Option<String>,
+    pub codecid: Option<u32>,
+    pub bandwidth: Option<u32>,
+    pub mime_type: Option<String>,
     pub url: Option<String>,
+    #[serde(default)]
+    pub backup_urls: Vec<String>,
     pub filesize: Option<u64>,
 }
 
@@ -168,11 +377,83 @@ pub struct SimplifiedPlayUrlData {
 }
 
 // 存储的登录信息
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StoredLoginData {
     pub cookies: String,
     pub user_profile: Option<UserProfile>,
     pub login_time: u64,
+    #[serde(default)]
+    pub refresh_token: Option<String>, // 扫码登录时下发，用于刷新 cookie
+}
+
+// 返回给前端的登录信息，cookie 和 refresh_token 只留在后端
+#[derive(Debug, Serialize, Clone)]
+pub struct LoginInfo {
+    pub user_profile: Option<UserProfile>,
+    pub login_time: u64,
+    pub has_refresh_token: bool, // 有 refresh_token 时后端会自动刷新 cookie
+}
+
+impl From<&StoredLoginData> for LoginInfo {
+    fn from(login_data: &StoredLoginData) -> Self {
+        Self {
+            user_profile: login_data.user_profile.clone(),
+            login_time: login_data.login_time,
+            has_refresh_token: login_data.refresh_token.is_some(),
+        }
+    }
+}
+
+// 保存的账号，每个账号有独立的 cookie 和用户信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountProfile {
+    pub id: String,
+    pub name: String,
+    pub login_data: StoredLoginData,
+}
+
+// 返回给前端的账号信息，不含 cookie
+#[derive(Debug, Serialize, Clone)]
+pub struct ProfileSummary {
+    pub id: String,
+    pub name: String,
+    pub user_profile: Option<UserProfile>,
+    pub login_time: u64,
+    pub active: bool,
+}
+
+// cookie/info：检查登录 cookie 是否需要刷新
+#[derive(Debug, Deserialize)]
+pub struct CookieInfoResponse {
+    pub code: i32,
+    pub message: String,
+    pub data: Option<CookieInfoData>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CookieInfoData {
+    pub refresh: bool,
+    pub timestamp: u64, // 毫秒，用于生成 correspondPath
+}
+
+// cookie/refresh：新的 cookie 通过 Set-Cookie 下发
+#[derive(Debug, Deserialize)]
+pub struct CookieRefreshResponse {
+    pub code: i32,
+    pub message: String,
+    pub data: Option<CookieRefreshData>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CookieRefreshData {
+    pub refresh_token: String,
+}
+
+// 只关心 code 和 message 的接口响应
+#[derive(Debug, Deserialize)]
+pub struct ApiStatusResponse {
+    pub code: i32,
+    pub message: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -241,13 +522,32 @@ pub struct UserInfoData {
     #[serde(rename = "is_senior_member")]
     pub is_senior_member: Option<u32>,
     #[serde(rename = "wbi_img")]
-    pub wbi_img: Option<serde_json::Value>,
+    pub wbi_img: Option<WbiImg>,
     #[serde(rename = "is_jury")]
     pub is_jury: Option<bool>,
     #[serde(rename = "name_render")]
     pub name_render: Option<serde_json::Value>,
 }
 
+// WBI 签名用的两张图片地址，文件名即 img_key / sub_key
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WbiImg {
+    pub img_url: String,
+    pub sub_url: String,
+}
+
+// /nav 未登录时也会返回 wbi_img，只解析这一部分
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NavWbiData {
+    pub wbi_img: WbiImg,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NavWbiResponse {
+    pub code: i32,
+    pub data: NavWbiData,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserInfoResponse {
     pub code: i32,
@@ -256,8 +556,259 @@ pub struct UserInfoResponse {
     pub data: UserInfoData,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DownloadProgress {
+// 下载选项，前端未传时使用默认值
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DownloadOptions {
+    pub segmented: bool,      // 是否对每个流多连接分段下载
+    pub connections: usize,   // 每个流的并发连接数
+    pub min_chunk_size: u64,  // 单个分段的最小字节数
+    pub segment_retries: u32, // 单个分段失败后的重试次数
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            segmented: false,
+            connections: 4,
+            min_chunk_size: 4 * 1024 * 1024,
+            segment_retries: 3,
+        }
+    }
+}
+
+// 下载内容
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadMode {
+    #[default]
+    Both,      // 下载音视频并合并
+    VideoOnly, // 只下载视频流
+    AudioOnly, // 只下载音频流，按编码封装为 m4a/flac/eac3
+}
+
+// 一个下载任务的全部参数
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadRequest {
+    pub video_data: VideoData,
+    #[serde(default)]
+    pub video_stream: Option<VideoStream>, // 仅下载音频时可以为空
+    #[serde(default)]
+    pub audio_stream: Option<AudioStream>, // durl 流自带音频，没有单独的音频流
+    pub cid: Option<u64>,
+    #[serde(default)]
+    pub mode: DownloadMode,
+    #[serde(default)]
+    pub options: DownloadOptions,
+    #[serde(default)]
+    pub profile_id: Option<String>, // 使用指定账号获取流地址，为空时使用当前账号
+}
+
+impl DownloadRequest {
+    // cid 对应的分P，未指定 cid 时为第一P
+    pub fn page(&self) -> Option<&VideoPage> {
+        match self.cid {
+            Some(cid) => self.video_data.pages.iter().find(|page| page.cid == cid),
+            None => self.video_data.pages.first(),
+        }
+    }
+
+    // 任务标题，多P视频附带分P序号和分P标题
+    pub fn display_title(&self) -> String {
+        match self.page() {
+            Some(page) if self.video_data.pages.len() > 1 => {
+                format!("{} - P{} {}", self.video_data.title, page.page, page.part)
+            }
+            _ => self.video_data.title.clone(),
+        }
+    }
+
+    // 按下载模式实际需要下载的视频流和音频流
+    pub fn selected_streams(&self) -> (Option<&VideoStream>, Option<&AudioStream>) {
+        let video = match self.mode {
+            DownloadMode::AudioOnly => None,
+            _ => self.video_stream.as_ref(),
+        };
+        let audio = match self.mode {
+            DownloadMode::VideoOnly => None,
+            _ => self.audio_stream.as_ref(),
+        };
+        (video, audio)
+    }
+}
+
+// 下载队列中任务的状态
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Paused,
+    Failed,
+    Done,
+}
+
+// 下载队列中的任务（download-status 事件也使用该结构）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadJob {
+    pub id: String,
+    pub title: String,
+    pub state: JobState,
     pub progress: f64,
+    #[serde(default, deserialize_with = "crate::error::stored_error")]
+    pub error: Option<crate::error::ErrorInfo>,
+    pub output: Option<String>,
+    pub created_at: u64,
+    pub request: DownloadRequest,
+}
+
+// 批量加入分P的结果：成功加入的任务和未能加入的分P
+#[derive(Debug, Serialize, Clone)]
+pub struct EnqueuePagesResult {
+    pub jobs: Vec<DownloadJob>,
+    pub failed: Vec<PageFailure>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PageFailure {
+    pub page: u32,
+    pub part: String, // 分P标题，分P不存在时为空
+    pub error: crate::error::ErrorInfo,
+}
+
+// 下载的流类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKind {
+    Video,
+    Audio,
+}
+
+impl StreamKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StreamKind::Video => "视频流",
+            StreamKind::Audio => "音频流",
+        }
+    }
+}
+
+// 自动选流时没有满足规则的流的处理方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionFallback {
+    Lowest, // 选择体积最小的流
+    Best,   // 忽略分辨率和大小限制，按偏好选择
+    Fail,   // 报错，不下载
+}
+
+// 自动选流规则
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SelectionRules {
+    pub max_height: Option<u32>,      // 最高分辨率（按短边），如 1080
+    pub codec_order: Vec<VideoCodec>, // 编码偏好，靠前的优先，未列出的排在最后
+    pub prefer_high_fps: bool,        // 同一分辨率下优先高帧率
+    pub premium_audio: bool,          // 有杜比/无损音轨时优先选择
+    pub max_size: Option<u64>,        // 视频+音频预估大小上限（字节）
+    pub fallback: SelectionFallback,
+}
+
+impl Default for SelectionRules {
+    fn default() -> Self {
+        Self {
+            max_height: None,
+            codec_order: vec![VideoCodec::Avc, VideoCodec::Hevc, VideoCodec::Av1],
+            prefer_high_fps: true,
+            premium_audio: false,
+            max_size: None,
+            fallback: SelectionFallback::Lowest,
+        }
+    }
+}
+
+// 应用设置（settings.json）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AppSettings {
+    pub selection: SelectionRules,
+    pub output_dir: Option<String>, // 为空时使用 <下载目录>/DILIDILI
+    pub filename_template: String,  // 单P视频的文件名模板，可用 / 表示子目录
+    pub page_template: String,      // 多P视频的文件名模板
+    pub requests_per_second: f64,   // API 请求速率上限，所有请求共享
+    pub proxy: ProxySettings,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            selection: SelectionRules::default(),
+            output_dir: None,
+            filename_template: "{title}".to_string(),
+            page_template: "{title} - P{page} {part}".to_string(),
+            requests_per_second: crate::ratelimit::DEFAULT_REQUESTS_PER_SECOND,
+            proxy: ProxySettings::default(),
+        }
+    }
+}
+
+// 代理协议
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyScheme {
+    #[default]
+    Http,
+    Https,
+    Socks5,
+}
+
+// 代理设置，API 请求和 CDN 媒体流分别开关
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub scheme: ProxyScheme,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub bypass: Vec<String>, // 不走代理的主机，如 "localhost"、".example.com"、"192.168.0.0/16"
+    pub api: bool,           // api.bilibili.com、passport 等接口走代理
+    pub media: bool,         // CDN 媒体流走代理
+}
+
+// 登录凭据的加密密钥来源
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialMode {
+    #[default]
+    Machine,    // 由本机标识派生，无需输入，换机器后无法解密
+    Passphrase, // 由用户口令派生，每次启动需要解锁
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CredentialStatus {
+    pub mode: CredentialMode,
+    pub locked: bool, // 口令模式下尚未解锁
+}
+
+// 风控冷却事件（api-cooldown），冷却期间下载队列暂停启动新任务
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiCooldown {
+    pub active: bool,
+    pub remaining_secs: u64,
+    pub reason: String,
+}
+
+// 下载进度事件（download-progress）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadProgress {
+    pub task_id: String,
+    pub stream: StreamKind,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    pub speed: f64,       // 字节/秒
+    pub eta: Option<u64>, // 剩余秒数，总大小未知时为空
+    pub progress: f64,    // 当前流的进度 0-100
+    pub overall: f64,     // 整个任务的进度 0-100
     pub message: String,
 } 
\ No newline at end of file
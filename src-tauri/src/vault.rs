@@ -0,0 +1,244 @@
+use crate::client::BiliClient;
+use crate::error::DilidiliError;
+use crate::types::*;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+// 派生参数和登录数据保存在同一个文件
+const LOGIN_STORE: &str = "login.json";
+
+// 加密字段的前缀，没有前缀的是旧版本保存的明文
+const SEALED_PREFIX: &str = "enc:v1:";
+
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+
+// 校验密钥用的固定明文
+const CHECK_FIELD: &str = "check";
+const CHECK_TEXT: &str = "dilidili";
+
+pub type CredentialKey = [u8; 32];
+
+// 密钥派生参数，保存在 login.json 的 vault 字段
+#[derive(Serialize, Deserialize)]
+struct VaultHeader {
+    mode: CredentialMode,
+    salt: String,  // base64
+    check: String, // 用派生的密钥加密的 CHECK_TEXT，解锁时据此判断口令是否正确
+}
+
+// Argon2id 派生 256 位密钥
+pub fn derive_key(secret: &[u8], salt: &[u8]) -> Result<CredentialKey, DilidiliError> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(secret, salt, &mut key)
+        .map_err(|e| DilidiliError::InvalidInput(format!("密钥派生失败: {}", e)))?;
+    Ok(key)
+}
+
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+// AES-256-GCM 加密为 "enc:v1:<base64(nonce + 密文)>"，字段名作为附加数据，密文不能挪到其他字段
+pub fn seal(key: &CredentialKey, field: &str, plaintext: &str) -> Result<String, DilidiliError> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(key.into())
+        .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: field.as_bytes() })
+        .map_err(|_| DilidiliError::InvalidInput("加密登录数据失败".to_string()))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", SEALED_PREFIX, BASE64.encode(sealed)))
+}
+
+// 解密 seal 的结果，密钥错误或内容被改动时返回错误
+pub fn open(key: &CredentialKey, field: &str, sealed: &str) -> Result<String, DilidiliError> {
+    let invalid = || DilidiliError::InvalidInput("口令错误或登录数据已损坏".to_string());
+
+    let data = sealed.strip_prefix(SEALED_PREFIX)
+        .and_then(|data| BASE64.decode(data).ok())
+        .filter(|data| data.len() > NONCE_LEN)
+        .ok_or_else(invalid)?;
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let plaintext = Aes256Gcm::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: field.as_bytes() })
+        .map_err(|_| invalid())?;
+    String::from_utf8(plaintext).map_err(|_| invalid())
+}
+
+fn machine_secret() -> Result<String, DilidiliError> {
+    machine_uid::get().map_err(|e| DilidiliError::Filesystem(format!("读取本机标识失败: {}", e)))
+}
+
+fn read_header(app_handle: &tauri::AppHandle) -> Result<Option<VaultHeader>, DilidiliError> {
+    use tauri_plugin_store::StoreExt;
+
+    let store = app_handle.store(LOGIN_STORE)?;
+    store.get("vault")
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| DilidiliError::Parse(format!("加密参数格式错误: {}", e)))
+}
+
+// 用新的盐派生密钥，派生参数写入 store 但不保存，由调用方和重新加密的登录数据一起保存
+fn replace_header(app_handle: &tauri::AppHandle, mode: CredentialMode, secret: &[u8]) -> Result<CredentialKey, DilidiliError> {
+    use tauri_plugin_store::StoreExt;
+
+    let (key, header) = new_header(mode, secret)?;
+    app_handle.store(LOGIN_STORE)?.set("vault", header);
+    Ok(key)
+}
+
+// 用随机盐派生新密钥，返回密钥和 login.json 中 vault 字段的内容
+pub fn new_header(mode: CredentialMode, secret: &[u8]) -> Result<(CredentialKey, serde_json::Value), DilidiliError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(secret, &salt)?;
+    let header = VaultHeader {
+        mode,
+        salt: BASE64.encode(salt),
+        check: seal(&key, CHECK_FIELD, CHECK_TEXT)?,
+    };
+    Ok((key, serde_json::to_value(&header)?))
+}
+
+// 按派生参数派生密钥并校验
+fn header_key(header: &VaultHeader, secret: &[u8]) -> Result<CredentialKey, DilidiliError> {
+    let salt = BASE64.decode(&header.salt)
+        .map_err(|_| DilidiliError::Parse("加密参数格式错误: salt".to_string()))?;
+    let key = derive_key(secret, &salt)?;
+    open(&key, CHECK_FIELD, &header.check)?;
+    Ok(key)
+}
+
+// 用 secret 校验 vault 字段并取得密钥；只读取，校验失败时不改动任何保存的数据
+pub fn open_header(header: &serde_json::Value, secret: &[u8]) -> Result<CredentialKey, DilidiliError> {
+    let header: VaultHeader = serde_json::from_value(header.clone())
+        .map_err(|e| DilidiliError::Parse(format!("加密参数格式错误: {}", e)))?;
+    header_key(&header, secret)
+}
+
+// 已解锁的凭据密钥，作为 Tauri managed state
+#[derive(Default)]
+pub struct Vault {
+    key: Mutex<Option<CredentialKey>>,
+}
+
+impl Vault {
+    // 当前的密钥：本机模式在首次使用时派生，口令模式需要先解锁
+    pub fn key(&self, app_handle: &tauri::AppHandle) -> Result<CredentialKey, DilidiliError> {
+        use tauri_plugin_store::StoreExt;
+
+        let mut cached = self.key.lock().unwrap();
+        if let Some(key) = *cached {
+            return Ok(key);
+        }
+
+        let key = match read_header(app_handle)? {
+            Some(header) if header.mode == CredentialMode::Passphrase => return Err(DilidiliError::CredentialsLocked),
+            // 校验失败（本机标识变化、参数损坏等）时原样保留登录数据，由用户决定是否重置
+            Some(header) => header_key(&header, machine_secret()?.as_bytes()).map_err(|e| {
+                println!("⚠️ 无法用本机密钥解密登录数据: {}", e);
+                DilidiliError::Parse(format!("无法用本机密钥解密登录数据，可重置登录数据后重新登录: {}", e))
+            })?,
+            None => {
+                let key = replace_header(app_handle, CredentialMode::Machine, machine_secret()?.as_bytes())?;
+                app_handle.store(LOGIN_STORE)?.save()?;
+                key
+            }
+        };
+        *cached = Some(key);
+        Ok(key)
+    }
+
+    fn set_key(&self, key: Option<CredentialKey>) {
+        *self.key.lock().unwrap() = key;
+    }
+
+    fn status(&self, app_handle: &tauri::AppHandle) -> Result<CredentialStatus, DilidiliError> {
+        let mode = read_header(app_handle)?.map(|header| header.mode).unwrap_or_default();
+        let locked = mode == CredentialMode::Passphrase && self.key.lock().unwrap().is_none();
+        Ok(CredentialStatus { mode, locked })
+    }
+}
+
+// 获取凭据加密方式和是否已解锁
+#[tauri::command]
+pub async fn get_credential_status(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>
+) -> Result<CredentialStatus, DilidiliError> {
+    vault.status(&app_handle)
+}
+
+// 用口令解锁登录数据，成功后之后的请求带上登录 cookie
+#[tauri::command]
+pub async fn unlock_credentials(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    client: tauri::State<'_, BiliClient>,
+    passphrase: String
+) -> Result<Option<LoginInfo>, DilidiliError> {
+    let header = read_header(&app_handle)?
+        .filter(|header| header.mode == CredentialMode::Passphrase)
+        .ok_or_else(|| DilidiliError::InvalidInput("没有设置口令".to_string()))?;
+    vault.set_key(Some(header_key(&header, passphrase.as_bytes())?));
+
+    let login_data = crate::storage::read_login_data(&app_handle)?;
+    if let Some(login_data) = &login_data {
+        client.cookie_jar().load(&login_data.cookies);
+    }
+    println!("🔓 登录数据已解锁");
+
+    // 锁定期间定时刷新无法进行，解锁后立即检查一次
+    crate::refresh::spawn_refresh(app_handle.clone());
+    Ok(login_data.as_ref().map(LoginInfo::from))
+}
+
+// 设置口令，None 表示改回本机密钥；需要先解锁，登录数据用新密钥重新加密
+#[tauri::command]
+pub async fn set_credential_passphrase(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    passphrase: Option<String>
+) -> Result<CredentialStatus, DilidiliError> {
+    let (mode, secret) = match passphrase {
+        Some(passphrase) if passphrase.is_empty() => return Err(DilidiliError::InvalidInput("口令不能为空".to_string())),
+        Some(passphrase) => (CredentialMode::Passphrase, passphrase),
+        None => (CredentialMode::Machine, machine_secret()?),
+    };
+
+    // 用当前密钥读出全部账号，再用新密钥重新加密，派生参数随账号一起保存
+    let (profiles, active) = crate::storage::load_profiles(&app_handle)?;
+    vault.set_key(Some(replace_header(&app_handle, mode, secret.as_bytes())?));
+    crate::storage::save_profiles(&app_handle, &profiles, active.as_deref())?;
+
+    println!("凭据加密方式: {:?}", mode);
+    vault.status(&app_handle)
+}
+
+// 忘记口令时清除全部账号和加密参数，改回本机密钥
+#[tauri::command]
+pub async fn reset_credentials(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    client: tauri::State<'_, BiliClient>
+) -> Result<(), DilidiliError> {
+    use tauri_plugin_store::StoreExt;
+
+    let store = app_handle.store(LOGIN_STORE)?;
+    store.delete("profiles");
+    store.delete("active_profile");
+    store.delete("login_data");
+    store.delete("vault");
+    store.save()?;
+    vault.set_key(None);
+    client.cookie_jar().clear();
+    Ok(())
+}
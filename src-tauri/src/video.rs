@@ -1,159 +1,300 @@
+use crate::api::BilibiliApi;
+use crate::client::BiliClient;
+use crate::error::DilidiliError;
 use crate::types::*;
-
-// 用户代理
-const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+use std::collections::HashMap;
 
 // 获取视频信息
 #[tauri::command]
-pub async fn get_video_info(video_id: String) -> Result<VideoData, String> {
-    let client = reqwest::Client::new();
-    let url = if video_id.starts_with("BV") {
-        format!("https://api.bilibili.com/x/web-interface/view?bvid={}", video_id)
-    } else if video_id.starts_with("av") {
-        let aid = video_id[2..].parse::<u64>().map_err(|_| "Invalid AV number".to_string())?;
-        format!("https://api.bilibili.com/x/web-interface/view?aid={}", aid)
+pub async fn get_video_info(client: tauri::State<'_, BiliClient>, video_id: String) -> Result<VideoData, DilidiliError> {
+    let bvid = resolve_video_id(&client, &video_id).await?;
+    fetch_video_info(client.inner(), &bvid).await
+}
+
+// 把视频链接、b23.tv 短链接、av 号等形式统一为 BV 号
+pub async fn resolve_video_id(client: &BiliClient, input: &str) -> Result<String, DilidiliError> {
+    let video_id = match crate::link::resolve_target(client, input).await? {
+        crate::link::BiliTarget::Video { id, .. } => id,
+        other => return Err(DilidiliError::InvalidInput(format!("不是视频链接: {:?}", other))),
+    };
+
+    // av 号在本地转换为 BV 号，统一按 BV 号查询
+    crate::bvid::canonical_bvid(&video_id)
+}
+
+pub async fn fetch_video_info(api: &dyn BilibiliApi, bvid: &str) -> Result<VideoData, DilidiliError> {
+    let text = api.view(bvid).await?;
+    
+    // 安全地截取前500个字符（不是字节）
+    let preview = if text.chars().count() > 500 {
+        text.chars().take(500).collect::<String>() + "..."
     } else {
-        return Err("Invalid video ID format".to_string());
+        text.clone()
     };
+    println!("API 原始响应: {}", preview);
+    
+    // 尝试解析 JSON
+    let response: VideoInfoResponse = serde_json::from_str(&text)?;
+    
+    if response.code != 0 {
+        return Err(DilidiliError::Api { code: response.code, message: response.message });
+    }
+    response.data.ok_or_else(|| DilidiliError::Parse("No video data".to_string()))
+}
 
-    let res = client.get(&url)
-        .header("User-Agent", USER_AGENT)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if res.status().is_success() {
-        // 先获取原始文本看看格式
-        let text = res.text().await.map_err(|e| e.to_string())?;
-        
-        // 安全地截取前500个字符（不是字节）
-        let preview = if text.chars().count() > 500 {
-            text.chars().take(500).collect::<String>() + "..."
-        } else {
-            text.clone()
-        };
-        println!("API 原始响应: {}", preview);
-        
-        // 尝试解析 JSON
-        let response: VideoInfoResponse = serde_json::from_str(&text)
-            .map_err(|e| format!("JSON 解析错误: {}", e))?;
-        
-        if response.code == 0 {
-            response.data.ok_or_else(|| "No video data".to_string())
-        } else {
-            Err(format!("API Error: {} - {}", response.code, response.message))
+// 常见清晰度代码的名称，accept_description 中没有时使用
+fn quality_name(qn: u32) -> String {
+    match qn {
+        127 => "8K 超高清",
+        126 => "杜比视界",
+        125 => "HDR 真彩色",
+        120 => "4K 超清",
+        116 => "1080P60 高帧率",
+        112 => "1080P+ 高码率",
+        80 => "1080P 高清",
+        74 => "720P60 高帧率",
+        64 => "720P 高清",
+        32 => "480P 清晰",
+        16 => "360P 流畅",
+        6 => "240P 极速",
+        _ => return format!("质量 {}", qn),
+    }
+    .to_string()
+}
+
+// 常见音质代码的名称
+fn audio_quality_name(id: u32) -> String {
+    match id {
+        30216 => "64K",
+        30232 => "132K",
+        30280 => "192K",
+        30250 => "杜比全景声",
+        30251 => "Hi-Res 无损",
+        _ => return format!("音质 {}", id),
+    }
+    .to_string()
+}
+
+// frame_rate 可能是 "29.412" 或 "30000/1001"
+fn parse_frame_rate(value: &str) -> Option<f64> {
+    match value.split_once('/') {
+        Some((num, den)) => {
+            let den = den.trim().parse::<f64>().ok().filter(|d| *d > 0.0)?;
+            Some(num.trim().parse::<f64>().ok()? / den)
         }
-    } else {
-        Err(format!("HTTP Error: {}", res.status()))
+        None => value.trim().parse().ok(),
+    }
+}
+
+// "video/mp4" -> "mp4"
+fn container_of(mime_type: Option<&str>, fallback: &str) -> String {
+    mime_type
+        .and_then(|m| m.split('/').nth(1))
+        .map(|sub| sub.to_string())
+        .unwrap_or_else(|| fallback.to_string())
+}
+
+// 生成 "1080P60 HEVC" 形式的标签，竖屏视频按短边计算
+fn video_label(quality: u32, width: Option<u32>, height: Option<u32>, frame_rate: Option<f64>, codec: VideoCodec, description: &str) -> String {
+    let short_side = match (width, height) {
+        (Some(w), Some(h)) => Some(w.min(h)),
+        (_, h) => h,
+    };
+    let resolution = match short_side {
+        Some(h) if h >= 4320 => "8K".to_string(),
+        Some(h) if h >= 2160 => "4K".to_string(),
+        Some(h) => format!("{}P", h),
+        None => return format!("{} {}", description, codec.name()),
+    };
+    let fps = match frame_rate {
+        Some(fps) if fps > 48.0 => format!("{}", fps.round() as u32),
+        _ => String::new(),
+    };
+    let extra = match quality {
+        126 => " 杜比视界",
+        125 => " HDR",
+        112 => " 高码率",
+        _ => "",
+    };
+    format!("{}{}{} {}", resolution, fps, extra, codec.name())
+}
+
+fn video_stream_from_dash(dash_video: &DashVideo, descriptions: &HashMap<u32, String>) -> VideoStream {
+    let codec = VideoCodec::detect(dash_video.codecid, dash_video.codecs.as_deref());
+    let frame_rate = dash_video.frame_rate.as_deref().and_then(parse_frame_rate);
+    let description = descriptions.get(&dash_video.id)
+        .cloned()
+        .unwrap_or_else(|| quality_name(dash_video.id));
+
+    VideoStream {
+        quality: dash_video.id,
+        format: container_of(dash_video.mime_type.as_deref(), "mp4"),
+        label: video_label(dash_video.id, dash_video.width, dash_video.height, frame_rate, codec, &description),
+        description,
+        codec,
+        codecs: dash_video.codecs.clone(),
+        codecid: dash_video.codecid,
+        width: dash_video.width,
+        height: dash_video.height,
+        frame_rate,
+        bandwidth: dash_video.bandwidth,
+        mime_type: dash_video.mime_type.clone(),
+        url: Some(dash_video.base_url.clone()),
+        backup_urls: dash_video.backup_url.clone().unwrap_or_default(),
+        filesize: None, // DASH格式通常不提供文件大小
+        segments: Vec::new(),
     }
 }
 
+// durl 响应只有当前清晰度一个流，由若干 FLV/MP4 分段组成
+fn video_stream_from_durl(data: &PlayUrlData, durl: &[DurlSegment], descriptions: &HashMap<u32, String>) -> Option<VideoStream> {
+    let mut segments: Vec<&DurlSegment> = durl.iter().collect();
+    segments.sort_by_key(|segment| segment.order);
+    let first = segments.first()?;
+
+    let format = if data.format.starts_with("mp4") { "mp4" } else { "flv" };
+    let description = descriptions.get(&data.quality)
+        .cloned()
+        .unwrap_or_else(|| quality_name(data.quality));
+
+    Some(VideoStream {
+        quality: data.quality,
+        format: format.to_string(),
+        label: format!("{} {}", description, format.to_uppercase()),
+        description,
+        codec: VideoCodec::detect(Some(data.video_codecid), None),
+        codecs: None,
+        codecid: Some(data.video_codecid),
+        width: None,
+        height: None,
+        frame_rate: None,
+        bandwidth: None,
+        mime_type: None,
+        url: Some(first.url.clone()),
+        backup_urls: first.backup_url.clone().unwrap_or_default(),
+        filesize: Some(segments.iter().map(|segment| segment.size).sum()),
+        segments: segments.iter().map(|segment| StreamSegment {
+            url: segment.url.clone(),
+            backup_urls: segment.backup_url.clone().unwrap_or_default(),
+            size: Some(segment.size),
+            length: Some(segment.length),
+        }).collect(),
+    })
+}
+
+fn audio_stream_from_dash(dash_audio: &DashAudio) -> AudioStream {
+    let codec = AudioCodec::detect(dash_audio.id, dash_audio.codecs.as_deref());
+    AudioStream {
+        quality: dash_audio.id,
+        format: match codec {
+            AudioCodec::Eac3 => "eac3".to_string(),
+            AudioCodec::Flac => "flac".to_string(),
+            _ => "m4a".to_string(), // DASH音频通常是m4a
+        },
+        description: audio_quality_name(dash_audio.id),
+        codec,
+        codecs: dash_audio.codecs.clone(),
+        codecid: dash_audio.codecid,
+        bandwidth: dash_audio.bandwidth,
+        mime_type: dash_audio.mime_type.clone(),
+        url: Some(dash_audio.base_url.clone()),
+        backup_urls: dash_audio.backup_url.clone().unwrap_or_default(),
+        filesize: None,
+    }
+}
+
+// 转换 DASH 数据，返回按清晰度排序的视频流和音频流
+fn dash_streams(dash: &DashData, descriptions: &HashMap<u32, String>) -> (Vec<VideoStream>, Vec<AudioStream>) {
+    let mut video_streams: Vec<VideoStream> = dash.video.iter()
+        .map(|dash_video| video_stream_from_dash(dash_video, descriptions))
+        .collect();
+    let mut audio_streams: Vec<AudioStream> = dash.audio.iter()
+        .map(audio_stream_from_dash)
+        .collect();
+
+    // 按清晰度从高到低分组，同一清晰度内 AV1、HEVC、AVC 依次排列
+    video_streams.sort_by(|a, b| b.quality.cmp(&a.quality).then(b.codecid.cmp(&a.codecid)));
+    audio_streams.sort_by_key(|stream| std::cmp::Reverse(stream.bandwidth));
+
+    // 杜比和无损音轨排在普通音轨之后，前端默认仍选第一路 AAC
+    if let Some(dolby_audio) = dash.dolby.as_ref().and_then(|dolby| dolby.audio.as_ref()) {
+        audio_streams.extend(dolby_audio.iter().map(audio_stream_from_dash));
+    }
+    if let Some(flac_audio) = dash.flac.as_ref().and_then(|flac| flac.audio.as_ref()) {
+        audio_streams.push(audio_stream_from_dash(flac_audio));
+    }
+
+    (video_streams, audio_streams)
+}
+
 // 获取视频流
 #[tauri::command]
-pub async fn get_video_streams(video_id: String, cid: u64, cookies: String) -> Result<SimplifiedPlayUrlData, String> {
-    let client = reqwest::Client::new();
-    
-    // B站视频流接口
-    let url = format!(
-        "https://api.bilibili.com/x/player/playurl?bvid={}&cid={}&qn=127&fourk=1&fnval=4048",
-        video_id, cid
-    );
+pub async fn get_video_streams(client: tauri::State<'_, BiliClient>, video_id: String, cid: u64) -> Result<SimplifiedPlayUrlData, DilidiliError> {
+    fetch_video_streams(client.inner(), &video_id, cid).await
+}
 
+pub async fn fetch_video_streams(api: &dyn BilibiliApi, video_id: &str, cid: u64) -> Result<SimplifiedPlayUrlData, DilidiliError> {
+    let video_id = crate::bvid::canonical_bvid(video_id)?;
+    
     println!("=== 获取视频流 ===");
-    println!("请求URL: {}", url);
-    println!("Cookies长度: {}", cookies.len());
+    println!("BV号: {}, cid: {}", video_id, cid);
 
-    let res = client.get(&url)
-        .header("User-Agent", USER_AGENT)
-        .header("Cookie", cookies)
-        .header("Referer", "https://www.bilibili.com/")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    println!("响应状态: {}", res.status());
-
-    if res.status().is_success() {
-        // 先获取原始文本看看格式
-        let text = res.text().await.map_err(|e| e.to_string())?;
-        
-        // 安全地截取前1000个字符（不是字节）
-        let preview = if text.chars().count() > 1000 {
-            text.chars().take(1000).collect::<String>() + "..."
-        } else {
-            text.clone()
-        };
-        println!("API 原始响应: {}", preview);
-        
-        // 尝试解析 JSON
-        let response: PlayUrlResponse = serde_json::from_str(&text)
-            .map_err(|e| format!("JSON 解析错误: {} - 原始响应: {}", e, preview))?;
-        
-        if response.code == 0 {
-            let data = response.data.ok_or_else(|| "No stream data".to_string())?;
-            
-            // 转换DASH格式到简化格式
-            let mut video_streams = Vec::new();
-            let mut audio_streams = Vec::new();
-            
-            // 处理视频流
-            for (index, dash_video) in data.dash.video.iter().enumerate() {
-                let quality_index = if index < data.accept_quality.len() { index } else { 0 };
-                let description_index = if index < data.accept_description.len() { index } else { 0 };
-                
-                video_streams.push(VideoStream {
-                    quality: data.accept_quality.get(quality_index).copied().unwrap_or(dash_video.id),
-                    format: "mp4".to_string(), // DASH通常是mp4
-                    description: data.accept_description.get(description_index).cloned().unwrap_or_else(|| format!("质量 {}", dash_video.id)),
-                    url: Some(dash_video.base_url.clone()),
-                    filesize: None, // DASH格式通常不提供文件大小
-                });
-            }
-            
-            // 处理音频流
-            for dash_audio in &data.dash.audio {
-                audio_streams.push(AudioStream {
-                    quality: dash_audio.id,
-                    format: "m4a".to_string(), // DASH音频通常是m4a
-                    url: Some(dash_audio.base_url.clone()),
-                    filesize: None,
-                });
-            }
-            
-            println!("转换后的视频流: {} 个", video_streams.len());
-            println!("转换后的音频流: {} 个", audio_streams.len());
-            
-            Ok(SimplifiedPlayUrlData {
-                video_streams,
-                audio_streams,
-            })
-        } else {
-            Err(format!("API Error: {} - {}", response.code, response.message))
-        }
+    // 先获取原始文本看看格式
+    let text = api.playurl(&video_id, cid).await?;
+    
+    // 安全地截取前1000个字符（不是字节）
+    let preview = if text.chars().count() > 1000 {
+        text.chars().take(1000).collect::<String>() + "..."
     } else {
-        Err(format!("HTTP Error: {}", res.status()))
+        text.clone()
+    };
+    println!("API 原始响应: {}", preview);
+    
+    // 尝试解析 JSON
+    let response: PlayUrlResponse = serde_json::from_str(&text)
+        .map_err(|e| DilidiliError::Parse(format!("JSON 解析错误: {} - 原始响应: {}", e, preview)))?;
+    
+    if response.code != 0 {
+        return Err(DilidiliError::Api { code: response.code, message: response.message });
     }
+
+    let data = response.data.ok_or_else(|| DilidiliError::Parse("No stream data".to_string()))?;
+    
+    // 转换DASH格式到简化格式
+    // accept_quality 与 accept_description 一一对应，但与 dash.video 的顺序无关
+    let descriptions: HashMap<u32, String> = data.accept_quality.iter()
+        .copied()
+        .zip(data.accept_description.iter().cloned())
+        .collect();
+
+    let (video_streams, audio_streams) = match (&data.dash, &data.durl) {
+        (Some(dash), _) => dash_streams(dash, &descriptions),
+        (None, Some(durl)) => {
+            println!("⚠️ 响应中没有 DASH 数据，使用 durl 分段 ({} 段, 格式 {})", durl.len(), data.format);
+            (video_stream_from_durl(&data, durl, &descriptions).into_iter().collect(), Vec::new())
+        }
+        (None, None) => return Err(DilidiliError::Parse("响应中既没有 dash 也没有 durl 数据".to_string())),
+    };
+
+    println!("转换后的视频流: {} 个", video_streams.len());
+    println!("转换后的音频流: {} 个", audio_streams.len());
+    
+    Ok(SimplifiedPlayUrlData {
+        video_streams,
+        audio_streams,
+    })
 }
 
 // 测试流URL可访问性
 #[tauri::command]
-pub async fn test_stream_url(url: String, cookies: String) -> Result<String, String> {
+pub async fn test_stream_url(client: tauri::State<'_, BiliClient>, url: String) -> Result<String, DilidiliError> {
     println!("=== 测试流URL可访问性 ===");
     println!("URL: {}", url);
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .user_agent(USER_AGENT)
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let response = client.head(&url)
-        .header("Cookie", &cookies)
-        .header("Referer", "https://www.bilibili.com/")
+    let response = client.media().head(&url)
         .header("Origin", "https://www.bilibili.com")
         .send()
-        .await
-        .map_err(|e| format!("请求失败: {}", e))?;
+        .await?;
 
     let status = response.status();
     let content_length = response.headers().get("content-length").cloned();
@@ -166,6 +307,6 @@ pub async fn test_stream_url(url: String, cookies: String) -> Result<String, Str
     if status.is_success() {
         Ok(format!("URL可访问，状态: {}", status))
     } else {
-        Err(format!("URL不可访问，状态: {}", status))
+        Err(DilidiliError::from_status(status))
     }
 } 
\ No newline at end of file
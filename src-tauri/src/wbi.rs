@@ -0,0 +1,144 @@
+use crate::client::BiliClient;
+use crate::error::DilidiliError;
+use crate::types::*;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// img_key 和 sub_key 每天更换，缓存一段时间后重新获取
+const KEY_TTL: Duration = Duration::from_secs(60 * 60);
+
+// 由 img_key + sub_key 打乱得到 mixin_key 的下标表
+const MIXIN_KEY_ENC_TAB: [usize; 64] = [
+    46, 47, 18, 2, 53, 8, 23, 32, 15, 50, 10, 31, 58, 3, 45, 35, 27, 43, 5, 49, 33, 9, 42, 19, 29, 28, 14, 39, 12,
+    38, 41, 13, 37, 48, 7, 16, 24, 55, 40, 61, 26, 17, 0, 1, 60, 51, 30, 4, 22, 25, 54, 21, 56, 59, 6, 63, 57, 62,
+    11, 36, 20, 34, 44, 52,
+];
+
+struct CachedKey {
+    mixin_key: String,
+    fetched_at: Instant,
+}
+
+static MIXIN_KEY: Mutex<Option<CachedKey>> = Mutex::new(None);
+
+// 返回 -352/-403 时说明签名被拒绝，丢弃缓存的 key 后重试
+pub fn is_signature_rejected(code: i32) -> bool {
+    matches!(code, -352 | -403)
+}
+
+pub fn invalidate() {
+    *MIXIN_KEY.lock().unwrap() = None;
+}
+
+// 取 URL 的文件名部分，如 ".../7cd084941338484aae1ad9425b84077c.png" -> "7cd0849..."
+fn key_from_url(url: &str) -> &str {
+    let name = url.rsplit('/').next().unwrap_or_default();
+    name.split('.').next().unwrap_or_default()
+}
+
+fn mixin_key(img_key: &str, sub_key: &str) -> String {
+    let raw: Vec<char> = format!("{}{}", img_key, sub_key).chars().collect();
+    MIXIN_KEY_ENC_TAB.iter()
+        .filter_map(|&i| raw.get(i))
+        .take(32)
+        .collect()
+}
+
+// 用 /nav 或登录后已拿到的 wbi_img 更新缓存
+pub fn remember_keys(wbi_img: &WbiImg) {
+    let img_key = key_from_url(&wbi_img.img_url);
+    let sub_key = key_from_url(&wbi_img.sub_url);
+    if img_key.is_empty() || sub_key.is_empty() {
+        return;
+    }
+
+    *MIXIN_KEY.lock().unwrap() = Some(CachedKey {
+        mixin_key: mixin_key(img_key, sub_key),
+        fetched_at: Instant::now(),
+    });
+}
+
+// 获取 mixin_key，缓存过期时从 /nav 重新获取（未登录时 /nav 同样返回 wbi_img）
+async fn get_mixin_key(client: &BiliClient) -> Result<String, DilidiliError> {
+    if let Some(cached) = MIXIN_KEY.lock().unwrap().as_ref() {
+        if cached.fetched_at.elapsed() < KEY_TTL {
+            return Ok(cached.mixin_key.clone());
+        }
+    }
+
+    println!("获取 WBI 签名密钥");
+    let text = crate::ratelimit::send(client.api().get("https://api.bilibili.com/x/web-interface/nav")).await?;
+
+    let body = serde_json::from_str::<NavWbiResponse>(&text)
+        .map_err(|e| DilidiliError::Parse(format!("WBI 密钥解析失败: {}", e)))?;
+    remember_keys(&body.data.wbi_img);
+
+    MIXIN_KEY.lock().unwrap()
+        .as_ref()
+        .map(|cached| cached.mixin_key.clone())
+        .ok_or_else(|| DilidiliError::Parse("WBI 密钥为空".to_string()))
+}
+
+// 与 JS 的 encodeURIComponent 一致：空格编码为 %20，十六进制大写
+fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// 按 WBI 规则签名：加入 wts，按键名排序，去掉值中的 !'()*，拼接 mixin_key 后取 md5 作为 w_rid
+pub fn sign_query(params: &[(&str, String)], mixin_key: &str, wts: u64) -> String {
+    let mut params: Vec<(String, String)> = params.iter()
+        .map(|(key, value)| (key.to_string(), value.chars().filter(|c| !"!'()*".contains(*c)).collect()))
+        .collect();
+    params.push(("wts".to_string(), wts.to_string()));
+    params.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let query = params.iter()
+        .map(|(key, value)| format!("{}={}", encode_component(key), encode_component(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let w_rid = format!("{:x}", md5::compute(format!("{}{}", query, mixin_key)));
+
+    format!("{}&w_rid={}", query, w_rid)
+}
+
+// 生成带 WBI 签名的完整请求地址
+pub async fn signed_url(client: &BiliClient, base: &str, params: &[(&str, String)]) -> Result<String, DilidiliError> {
+    let mixin_key = get_mixin_key(client).await?;
+    let wts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    Ok(format!("{}?{}", base, sign_query(params, &mixin_key, wts)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 公开文档中的签名示例
+    const IMG_URL: &str = "https://i0.hdslb.com/bfs/wbi/7cd084941338484aae1ad9425b84077c.png";
+    const SUB_URL: &str = "https://i0.hdslb.com/bfs/wbi/4932caff0ff746eab6f01bf08b70ac45.png";
+
+    #[test]
+    fn signs_documented_example() {
+        let mixin_key = mixin_key(key_from_url(IMG_URL), key_from_url(SUB_URL));
+        assert_eq!(mixin_key, "ea1db124af3c7062474693fa704f4ff8");
+
+        let params = [("foo", "114".to_string()), ("bar", "514".to_string()), ("zab", "1919810".to_string())];
+        assert_eq!(
+            sign_query(&params, &mixin_key, 1702204169),
+            "bar=514&foo=114&wts=1702204169&zab=1919810&w_rid=8f6f2b5b3d485fe1886cec6a0be8c5d4"
+        );
+    }
+
+    #[test]
+    fn filters_and_encodes_values() {
+        let params = [("keyword", "a b!'()*c".to_string())];
+        let query = sign_query(&params, "", 1);
+        assert!(query.starts_with("keyword=a%20bc&wts=1&w_rid="));
+    }
+}
@@ -0,0 +1,226 @@
+// 使用录制的接口响应和本地媒体服务，离线测试接口解析和下载流水线
+use DILIDILI_lib::download::Downloader;
+use DILIDILI_lib::fixture::{media_bytes, FixtureApi};
+use DILIDILI_lib::types::*;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const BVID: &str = "BV1GJ411x7h7";
+const CID_DASH: u64 = 137649199;
+const CID_DURL: u64 = 137649200;
+const QRCODE_KEY: &str = "8a2f5d0e6b3c4a1f9e7d2c0b5a4f3e21";
+const REFRESH_TOKEN: &str = "f0e1d2c3b4a5968778695a4b3c2d1e0f";
+
+async fn fixture_api() -> FixtureApi {
+    FixtureApi::start(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"))
+        .await
+        .expect("启动 fixture 媒体服务失败")
+}
+
+// 每个测试使用独立的输出目录
+fn output_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dilidili-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+// 不使用 ffmpeg，结果与本机环境无关：仅视频时直接保留视频流，需要合并时返回错误
+fn downloader(api: FixtureApi, dir: &Path) -> Downloader {
+    let settings = AppSettings::default();
+    Downloader {
+        api: Arc::new(api),
+        output_dir: dir.to_path_buf(),
+        filename_template: settings.filename_template,
+        page_template: settings.page_template,
+        ffmpeg: None,
+    }
+}
+
+// 媒体地址对应的伪造内容
+fn expected_bytes(url: &str) -> Vec<u8> {
+    let url = url::Url::parse(url).unwrap();
+    let size = url.query_pairs()
+        .find(|(key, _)| key == "size")
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap();
+    media_bytes(url.path(), 0..size)
+}
+
+fn files_in(dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = std::fs::read_dir(dir).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    files
+}
+
+fn request(video_data: VideoData, cid: u64, video: Option<VideoStream>, audio: Option<AudioStream>, mode: DownloadMode) -> DownloadRequest {
+    DownloadRequest {
+        video_data,
+        video_stream: video,
+        audio_stream: audio,
+        cid: Some(cid),
+        mode,
+        options: DownloadOptions::default(),
+        profile_id: None,
+    }
+}
+
+#[tokio::test]
+async fn nav_returns_user_profile() {
+    let api = fixture_api().await;
+    let profile = DILIDILI_lib::auth::fetch_user_info(&api).await.unwrap();
+
+    assert_eq!(profile.name, "测试用户");
+    assert_eq!(profile.mid, 123456789);
+    assert_eq!(profile.vip_type, 1);
+}
+
+#[tokio::test]
+async fn qr_login_generate_and_poll() {
+    let api = fixture_api().await;
+
+    let qr = DILIDILI_lib::auth::fetch_qr_code(&api).await.unwrap();
+    assert_eq!(qr.qrcode_key, QRCODE_KEY);
+    assert!(qr.url.contains(QRCODE_KEY));
+
+    let confirmed = DILIDILI_lib::auth::fetch_poll_status(&api, &qr.qrcode_key).await.unwrap();
+    assert_eq!(confirmed.code, 0);
+    assert_eq!(confirmed.refresh_token.as_deref(), Some("f0e1d2c3b4a5968778695a4b3c2d1e0f"));
+
+    let expired = DILIDILI_lib::auth::fetch_poll_status(&api, "expired").await.unwrap();
+    assert_eq!(expired.code, 86038);
+}
+
+#[tokio::test]
+async fn cookie_refresh_runs_only_when_needed() {
+    let api = fixture_api().await;
+
+    api.set_cookies("SESSDATA=fresh; bili_jct=9d8c7b6a5f4e3d2c1b0a9f8e7d6c5b4a");
+    assert_eq!(DILIDILI_lib::refresh::refresh_cookies(&api, REFRESH_TOKEN).await.unwrap(), None);
+
+    api.set_cookies("SESSDATA=stale; bili_jct=3c7a9e2b1d4f6a8c0e2b4d6f8a1c3e5b");
+    let new_token = DILIDILI_lib::refresh::refresh_cookies(&api, REFRESH_TOKEN).await.unwrap();
+    assert_eq!(new_token.as_deref(), Some("0a1b2c3d4e5f60718293a4b5c6d7e8f9"));
+
+    // 没有 bili_jct 时无法刷新
+    api.set_cookies("SESSDATA=stale");
+    let error = DILIDILI_lib::refresh::refresh_cookies(&api, REFRESH_TOKEN).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+}
+
+#[test]
+fn correspond_path_is_oaep_encrypted() {
+    // 1024 位公钥加密结果为 128 字节；OAEP 带随机填充，每次结果不同
+    let first = DILIDILI_lib::refresh::correspond_path(1734963138171).unwrap();
+    let second = DILIDILI_lib::refresh::correspond_path(1734963138171).unwrap();
+    assert_eq!(first.len(), 256);
+    assert!(first.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')));
+    assert_ne!(first, second);
+}
+
+#[tokio::test]
+async fn view_and_playurl_are_parsed() {
+    let api = fixture_api().await;
+
+    let video = DILIDILI_lib::video::fetch_video_info(&api, BVID).await.unwrap();
+    assert_eq!(video.aid, 80433022);
+    assert_eq!(video.pages.len(), 2);
+    assert_eq!(video.owner_info.name, "索尼音乐中国");
+
+    // av 号在本地转换为 BV 号
+    let streams = DILIDILI_lib::video::fetch_video_streams(&api, "av80433022", CID_DASH).await.unwrap();
+    let qualities: Vec<u32> = streams.video_streams.iter().map(|s| s.quality).collect();
+    assert_eq!(qualities, vec![80, 64]);
+    assert_eq!(streams.video_streams[0].description, "高清 1080P");
+    assert_eq!(streams.video_streams[0].backup_urls.len(), 1);
+    assert_eq!(streams.audio_streams[0].quality, 30280);
+    assert!(streams.video_streams[0].url.as_deref().unwrap().starts_with(api.media_base()));
+
+    let durl = DILIDILI_lib::video::fetch_video_streams(&api, BVID, CID_DURL).await.unwrap();
+    assert_eq!(durl.video_streams.len(), 1);
+    assert_eq!(durl.video_streams[0].format, "mp4");
+    assert_eq!(durl.video_streams[0].segments.len(), 1);
+    assert!(durl.audio_streams.is_empty());
+}
+
+#[tokio::test]
+async fn missing_fixture_is_reported() {
+    let api = fixture_api().await;
+    let error = DILIDILI_lib::video::fetch_video_info(&api, "BV1xx411c7mD").await.unwrap_err();
+    assert_eq!(error.code(), "filesystem");
+}
+
+#[tokio::test]
+async fn dash_download_fails_over_to_mirror() {
+    let dir = output_dir("dash");
+    let downloader = downloader(fixture_api().await, &dir);
+
+    let video_data = DILIDILI_lib::video::fetch_video_info(downloader.api.as_ref(), BVID).await.unwrap();
+    let streams = DILIDILI_lib::video::fetch_video_streams(downloader.api.as_ref(), BVID, CID_DASH).await.unwrap();
+    let video = streams.video_streams[0].clone();
+    let audio = streams.audio_streams[0].clone();
+    let expected = expected_bytes(&video.backup_urls[0]);
+
+    let progress = RefCell::new(0.0f64);
+    let request = request(video_data, CID_DASH, Some(video), Some(audio), DownloadMode::Both);
+    let error = downloader.run(&request, "dash", |p| *progress.borrow_mut() = p.overall).await.unwrap_err();
+
+    // 主地址返回 404 后切换到 backup_url；没有 ffmpeg 时无法合并，音视频流都保留给重试
+    assert_eq!(error.code(), "ffmpeg_not_found");
+    let files = files_in(&dir);
+    assert_eq!(files.len(), 2);
+    let video_file = files.iter().find(|name| name.ends_with("_video.m4s")).unwrap();
+    assert!(files.iter().any(|name| name.ends_with("_audio.m4s")));
+    assert_eq!(std::fs::read(dir.join(video_file)).unwrap(), expected);
+    assert!(*progress.borrow() >= 99.9);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn segmented_download_reassembles_stream() {
+    let dir = output_dir("segmented");
+    let downloader = downloader(fixture_api().await, &dir);
+
+    let video_data = DILIDILI_lib::video::fetch_video_info(downloader.api.as_ref(), BVID).await.unwrap();
+    let streams = DILIDILI_lib::video::fetch_video_streams(downloader.api.as_ref(), BVID, CID_DASH).await.unwrap();
+    let video = streams.video_streams.iter().find(|s| s.quality == 64).unwrap().clone();
+    let expected = expected_bytes(video.url.as_deref().unwrap());
+
+    let mut request = request(video_data, CID_DASH, Some(video), None, DownloadMode::VideoOnly);
+    request.options = DownloadOptions {
+        segmented: true,
+        connections: 4,
+        min_chunk_size: 16 * 1024,
+        segment_retries: 1,
+    };
+    downloader.run(&request, "segmented", |_| {}).await.unwrap();
+
+    let name = "Never Gonna Give You Up - P1 Never Gonna Give You Up.mp4";
+    assert_eq!(files_in(&dir), vec![name.to_string()]);
+    assert_eq!(std::fs::read(dir.join(name)).unwrap(), expected);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn durl_download_keeps_single_segment() {
+    let dir = output_dir("durl");
+    let downloader = downloader(fixture_api().await, &dir);
+
+    let video_data = DILIDILI_lib::video::fetch_video_info(downloader.api.as_ref(), BVID).await.unwrap();
+    let streams = DILIDILI_lib::video::fetch_video_streams(downloader.api.as_ref(), BVID, CID_DURL).await.unwrap();
+    let video = streams.video_streams[0].clone();
+    let expected = expected_bytes(&video.segments[0].url);
+
+    let request = request(video_data, CID_DURL, Some(video), None, DownloadMode::Both);
+    downloader.run(&request, "durl", |_| {}).await.unwrap();
+
+    let name = "Never Gonna Give You Up - P2 Live.mp4";
+    assert_eq!(files_in(&dir), vec![name.to_string()]);
+    assert_eq!(std::fs::read(dir.join(name)).unwrap(), expected);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,49 @@
+// 二维码渲染：data URI 格式、PNG 尺寸和终端输出
+use base64::Engine;
+use DILIDILI_lib::qr::{render_image, render_terminal};
+use DILIDILI_lib::types::*;
+
+const LOGIN_URL: &str = "https://account.bilibili.com/h5/account-h5/auth/scan-web?navhide=1&qrcode_key=8a2f5d0e6b3c4a1f9e7d2c0b5a4f3e21";
+
+fn decode_data_uri(uri: &str, mime: &str) -> Vec<u8> {
+    let data = uri.strip_prefix(&format!("data:{};base64,", mime)).expect("data URI 前缀不正确");
+    base64::engine::general_purpose::STANDARD.decode(data).unwrap()
+}
+
+#[test]
+fn image_is_rendered_as_data_uris() {
+    let options = QrRenderOptions { size: 256, ec_level: QrEcLevel::H };
+    let image = render_image(LOGIN_URL, &options).unwrap();
+
+    let svg = String::from_utf8(decode_data_uri(&image.svg, "image/svg+xml")).unwrap();
+    assert!(svg.contains("<svg"));
+
+    // PNG 边长不小于要求的尺寸，四周留白为白色
+    let png = decode_data_uri(&image.png, "image/png");
+    let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.width, info.height);
+    assert!(info.width >= 256);
+    assert_eq!(pixels[0], 0xff);
+    assert!(pixels.contains(&0));
+}
+
+#[test]
+fn invalid_size_is_rejected() {
+    let options = QrRenderOptions { size: 16, ..Default::default() };
+    let error = render_image(LOGIN_URL, &options).unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+}
+
+#[test]
+fn terminal_output_uses_half_blocks() {
+    let text = render_terminal(LOGIN_URL, QrEcLevel::M).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    let width = lines[0].chars().count();
+
+    // 一行字符对应两行模块，二维码是正方形
+    assert!(lines.iter().all(|line| line.chars().count() == width));
+    assert_eq!(lines.len(), width.div_ceil(2));
+    assert!(text.chars().all(|c| matches!(c, ' ' | '▀' | '▄' | '█' | '\n')));
+}
@@ -0,0 +1,65 @@
+// 登录凭据加密：AES-GCM 加解密、字段绑定和密钥派生
+use DILIDILI_lib::types::CredentialMode;
+use DILIDILI_lib::vault::{derive_key, is_sealed, new_header, open, open_header, seal};
+
+const COOKIES: &str = "SESSDATA=abc%2C123; bili_jct=3c7a9e2b1d4f6a8c0e2b4d6f8a1c3e5b";
+const SALT: &[u8] = b"0123456789abcdef";
+
+#[test]
+fn sealed_fields_round_trip() {
+    let key = derive_key(b"machine-id", SALT).unwrap();
+    let sealed = seal(&key, "cookies", COOKIES).unwrap();
+
+    assert!(is_sealed(&sealed));
+    assert!(!is_sealed(COOKIES));
+    assert!(!sealed.contains("SESSDATA"));
+    assert_eq!(open(&key, "cookies", &sealed).unwrap(), COOKIES);
+
+    // 随机 nonce，同一明文每次密文不同
+    assert_ne!(seal(&key, "cookies", COOKIES).unwrap(), sealed);
+}
+
+#[test]
+fn wrong_key_field_or_tampering_is_rejected() {
+    let key = derive_key(b"correct horse", SALT).unwrap();
+    let sealed = seal(&key, "cookies", COOKIES).unwrap();
+
+    let wrong_key = derive_key(b"battery staple", SALT).unwrap();
+    assert_eq!(open(&wrong_key, "cookies", &sealed).unwrap_err().code(), "invalid_input");
+
+    // 密文不能挪到其他字段使用
+    assert!(open(&key, "refresh_token", &sealed).is_err());
+
+    let mut tampered = sealed.clone();
+    let last = tampered.pop().unwrap();
+    tampered.push(if last == 'A' { 'B' } else { 'A' });
+    assert!(open(&key, "cookies", &tampered).is_err());
+    assert!(open(&key, "cookies", COOKIES).is_err());
+}
+
+#[test]
+fn key_depends_on_secret_and_salt() {
+    let key = derive_key(b"machine-id", SALT).unwrap();
+    assert_eq!(derive_key(b"machine-id", SALT).unwrap(), key);
+    assert_ne!(derive_key(b"machine-id", b"fedcba9876543210").unwrap(), key);
+    assert_ne!(derive_key(b"other-machine", SALT).unwrap(), key);
+}
+
+#[test]
+fn bad_check_value_keeps_stored_data() {
+    let (key, header) = new_header(CredentialMode::Machine, b"machine-id").unwrap();
+    let sealed = seal(&key, "cookies", COOKIES).unwrap();
+    assert_eq!(open_header(&header, b"machine-id").unwrap(), key);
+
+    // 本机标识变化或校验值损坏时报错，派生参数和加密的登录数据都不被改动
+    assert!(open_header(&header, b"other-machine").is_err());
+    let mut corrupted = header.clone();
+    corrupted["check"] = serde_json::json!("enc:v1:AAAA");
+    assert!(open_header(&corrupted, b"machine-id").is_err());
+    let mut bad_salt = header.clone();
+    bad_salt["salt"] = serde_json::json!("not base64!");
+    assert_eq!(open_header(&bad_salt, b"machine-id").unwrap_err().code(), "parse");
+
+    assert_eq!(open_header(&header, b"machine-id").unwrap(), key);
+    assert_eq!(open(&key, "cookies", &sealed).unwrap(), COOKIES);
+}
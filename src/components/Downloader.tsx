@@ -1,9 +1,10 @@
-import { useState } from 'react';
+import { useEffect, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { useAppStore } from '../store/appStore';
-import { VideoData, PlayUrlData} from '../types/bilibili';
+import { VideoData, PlayUrlData, DownloadProgress, DownloadMode, EnqueuePagesResult, BiliTarget, ApiCooldown } from '../types/bilibili';
 import CustomSelect from './ui/CustomSelect';
-import { isTauriAvailable } from '../lib/tauri';
+import { formatError, isTauriAvailable } from '../lib/tauri';
 import { useMessage } from './ui/MessageContext';
 
 export default function Downloader() {
@@ -12,30 +13,47 @@ export default function Downloader() {
   const [currentVideoData, setCurrentVideoData] = useState<VideoData | null>(null);
   const [showQualitySelector, setShowQualitySelector] = useState(false);
   const [streamData, setStreamData] = useState<PlayUrlData | null>(null);
+  const [selectedAudioIndex, setSelectedAudioIndex] = useState(0);
+  const [downloadMode, setDownloadMode] = useState<DownloadMode>('both');
+  const [pageSelection, setPageSelection] = useState('');
+  const [selectedCid, setSelectedCid] = useState<number | undefined>();
   const [exportFolder, setExportFolder] = useState<string>('');
   
-  const { downloads: downloadQueue, addDownloadItem, updateDownloadProgress, updateDownloadStatus, isLoggedIn, cookies } = useAppStore();
+  const { downloads: downloadQueue, addDownloadItem, updateDownloadProgress, updateDownloadStatus, isLoggedIn } = useAppStore();
   const { error, warning } = useMessage();
 
-  // 提取视频 ID 的函数
-  const extractVideoId = (url: string): string | null => {
-    const patterns = [
-      /(?:https?:\/\/)?(?:www\.)?bilibili\.com\/video\/(BV[a-zA-Z0-9]+)/,
-      /(?:https?:\/\/)?(?:www\.)?bilibili\.com\/video\/av(\d+)/,
-      /(?:https?:\/\/)?b23\.tv\/([a-zA-Z0-9]+)/,
-      /^(BV[a-zA-Z0-9]+)$/,
-      /^av(\d+)$/
-    ];
-
-    for (const pattern of patterns) {
-      const match = url.match(pattern);
-      if (match) {
-        return match[1];
+  // 监听后端推送的下载进度，overall 为整个任务的进度
+  useEffect(() => {
+    if (!isTauriAvailable()) return;
+
+    const unlisten = listen<DownloadProgress>('download-progress', (event) => {
+      const { task_id, overall } = event.payload;
+      updateDownloadProgress(task_id, Math.round(overall));
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [updateDownloadProgress]);
+
+  // 触发B站风控时后端会暂停请求一段时间
+  useEffect(() => {
+    if (!isTauriAvailable()) return;
+
+    const unlisten = listen<ApiCooldown>('api-cooldown', (event) => {
+      const { active, remaining_secs } = event.payload;
+      if (active) {
+        warning(`请求过于频繁，已暂停 ${remaining_secs} 秒，下载队列将在冷却结束后继续`);
+      } else {
+        warning('请求冷却结束，已恢复下载队列');
       }
-    }
-    return null;
-  };  
-  
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [warning]);
+
   // 处理视频 URL 分析
   const handleAnalyzeVideo = async () => {
     if (!isTauriAvailable()) {
@@ -48,27 +66,38 @@ export default function Downloader() {
       return;
     }
 
-    const videoId = extractVideoId(videoUrl.trim());
-    
-    if (!videoId) {
-      error('无效的视频链接格式', '支持格式：\n- https://www.bilibili.com/video/BV...\n- https://www.bilibili.com/video/av...\n- https://b23.tv/...\n- BV...\n- av...');
+    setIsAnalyzing(true);
+
+    // 链接由后端解析，支持 ?p= 分P、m站、b23.tv 短链接等
+    let target: BiliTarget;
+    try {
+      target = await invoke<BiliTarget>('parse_link', { input: videoUrl.trim() });
+    } catch (parseError) {
+      setIsAnalyzing(false);
+      error('无效的视频链接格式', `${formatError(parseError)}\n支持格式：\n- https://www.bilibili.com/video/BV...\n- https://www.bilibili.com/video/av...\n- https://b23.tv/...\n- BV...\n- av...`);
       return;
     }
 
-    setIsAnalyzing(true);
+    if (target.type !== 'video') {
+      setIsAnalyzing(false);
+      warning(`暂不支持下载该类型的链接：${target.type}`);
+      return;
+    }
 
     try {
-      const videoData: VideoData = await invoke('get_video_info', { videoId });
+      const videoData: VideoData = await invoke('get_video_info', { videoId: target.id });
       setCurrentVideoData(videoData);
+      const page = videoData.pages.find((p) => p.page === target.page) ?? videoData.pages[0];
+      setSelectedCid(page.cid);
       
-      if (isLoggedIn && cookies) {
+      if (isLoggedIn) {
         try {
           const streamData: PlayUrlData = await invoke('get_video_streams', {
             videoId: videoData.bvid,
-            cid: videoData.pages[0].cid,
-            cookies
+            cid: page.cid
           });
           setStreamData(streamData);
+          setSelectedAudioIndex(0);
           setShowQualitySelector(true);
         } catch (streamError) {
           addDownloadItem({
@@ -79,7 +108,7 @@ export default function Downloader() {
             status: 'pending'
           });
           setVideoUrl('');
-          warning(`获取视频流失败：${streamError}`);
+          warning(`获取视频流失败：${formatError(streamError)}`);
         }
       } else {
         warning('请先登录以获取视频下载链接');
@@ -93,7 +122,7 @@ export default function Downloader() {
         setVideoUrl('');
       }
     } catch (error) {
-      warning(`分析视频失败：${error}`);
+      warning(`分析视频失败：${formatError(error)}`);
     } finally {
       setIsAnalyzing(false);
     }
@@ -112,7 +141,9 @@ export default function Downloader() {
       videoData: currentVideoData,
       selectedQuality: {
         video: videoStream,
-        audio: audioStream
+        audio: audioStream,
+        mode: downloadMode,
+        cid: selectedCid
       }
     };
 
@@ -138,7 +169,7 @@ export default function Downloader() {
       return;
     }
 
-    if (!isLoggedIn || !cookies) {
+    if (!isLoggedIn) {
       updateDownloadStatus(downloadItem.id, 'failed');
       warning('请先登录后再下载');
       return;
@@ -152,7 +183,9 @@ export default function Downloader() {
         videoData: downloadItem.videoData,
         videoStream: downloadItem.selectedQuality.video,
         audioStream: downloadItem.selectedQuality.audio,
-        cookies: cookies
+        mode: downloadItem.selectedQuality.mode,
+        cid: downloadItem.selectedQuality.cid,
+        taskId: downloadItem.id
       });
 
       updateDownloadStatus(downloadItem.id, 'completed');
@@ -160,7 +193,32 @@ export default function Downloader() {
       warning(`下载完成: ${result}`);
     } catch (error) {
       updateDownloadStatus(downloadItem.id, 'failed');
-      warning(`下载失败：${error}`);
+      warning(`下载失败：${formatError(error)}`);
+    }
+  };
+
+  // 多P视频批量加入后端下载队列，按设置中的规则自动选流
+  const enqueueSelectedPages = async () => {
+    if (!currentVideoData) return;
+
+    try {
+      const { jobs, failed } = await invoke<EnqueuePagesResult>('enqueue_pages', {
+        videoId: currentVideoData.bvid,
+        pages: pageSelection,
+        mode: downloadMode
+      });
+      if (failed.length > 0) {
+        const skipped = failed.map(f => `P${f.page}：${formatError(f.error)}`).join('\n');
+        error(`已加入下载队列: ${jobs.length} 个分P，${failed.length} 个分P未加入`, skipped);
+      } else {
+        warning(`已加入下载队列: ${jobs.length} 个分P`);
+      }
+      setShowQualitySelector(false);
+      setCurrentVideoData(null);
+      setStreamData(null);
+      setPageSelection('');
+    } catch (err) {
+      warning(`批量下载失败：${formatError(err)}`);
     }
   };
 
@@ -183,7 +241,7 @@ export default function Downloader() {
       setExportFolder(folder);
       warning(`已选择导出文件夹: ${folder}`);
     } catch (error) {
-      warning(`选择文件夹失败: ${error}`);
+      warning(`选择文件夹失败: ${formatError(error)}`);
     }
   };
 
@@ -207,7 +265,7 @@ export default function Downloader() {
       });
       warning(`文件导出成功: ${exportedPath}`);
     } catch (error) {
-      warning(`导出失败: ${error}`);
+      warning(`导出失败: ${formatError(error)}`);
     }
   };
 
@@ -227,7 +285,7 @@ export default function Downloader() {
       });
       warning(`格式转换成功: ${result}`);
     } catch (error) {
-      warning(`格式转换失败: ${error}`);
+      warning(`格式转换失败: ${formatError(error)}`);
     }
   };
 
@@ -247,7 +305,7 @@ export default function Downloader() {
       });
       warning(`音频提取成功: ${result}`);
     } catch (error) {
-      warning(`音频提取失败: ${error}`);
+      warning(`音频提取失败: ${formatError(error)}`);
     }
   };
 
@@ -261,7 +319,7 @@ export default function Downloader() {
     try {
       await invoke('open_folder', { folderPath });
     } catch (error) {
-      warning(`打开文件夹失败: ${error}`);
+      warning(`打开文件夹失败: ${formatError(error)}`);
     }
   };
 
@@ -355,7 +413,78 @@ export default function Downloader() {
                   </div>
                 </div>
 
-                {/* 清晰度选择 */}
+                {/* 多P视频：分P范围批量下载 */}
+                {currentVideoData.pages.length > 1 && (
+                  <div className="space-y-3 mb-4">
+                    <label className="block text-lg font-semibold text-gray-700 dark:text-gray-300">
+                      批量下载分P（共 {currentVideoData.pages.length} P）：
+                    </label>
+                    <div className="flex space-x-3">
+                      <input
+                        type="text"
+                        value={pageSelection}
+                        onChange={(e) => setPageSelection(e.target.value)}
+                        placeholder="如 1-3,5,8-，留空为全部"
+                        className="flex-1 px-4 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
+                      />
+                      <button
+                        onClick={enqueueSelectedPages}
+                        className="px-4 py-2 bg-blue-500 hover:bg-blue-600 text-white rounded-lg font-medium transition-all"
+                      >
+                        批量下载
+                      </button>
+                    </div>
+                  </div>
+                )}
+
+                {/* 下载内容 */}
+                <div className="space-y-3 relative mb-4">
+                  <label className="block text-lg font-semibold text-gray-700 dark:text-gray-300">
+                    下载内容：
+                  </label>
+                  <CustomSelect
+                    options={[
+                      { value: 'both', label: '音视频', description: '下载视频和音频并合并' },
+                      { value: 'video_only', label: '仅视频', description: '只下载视频流' },
+                      { value: 'audio_only', label: '仅音频', description: '按编码保存为 m4a / flac / eac3' }
+                    ]}
+                    value={downloadMode}
+                    onChange={(value) => setDownloadMode(value as DownloadMode)}
+                  />
+                </div>
+
+                {/* 音轨选择（大会员可选杜比全景声 / Hi-Res 无损） */}
+                {downloadMode !== 'video_only' && streamData.audio_streams.length > 1 && (
+                  <div className="space-y-3 relative mb-4">
+                    <label className="block text-lg font-semibold text-gray-700 dark:text-gray-300">
+                      选择音轨：
+                    </label>
+                    <CustomSelect
+                      options={streamData.audio_streams.map((audioStream, index) => ({
+                        value: index,
+                        label: audioStream.description || `音质 ${audioStream.quality}`,
+                        description: audioStream.codecs ? `编码: ${audioStream.codecs}` : undefined,
+                        filesize: audioStream.filesize
+                      }))}
+                      value={selectedAudioIndex}
+                      onChange={(selectedIndex) => setSelectedAudioIndex(selectedIndex as number)}
+                    />
+                  </div>
+                )}
+
+                {/* 仅音频时不需要选择清晰度 */}
+                {downloadMode === 'audio_only' ? (
+                  <div className="flex justify-end">
+                    <button
+                      onClick={() => handleQualitySelect(undefined, streamData.audio_streams[selectedAudioIndex] ?? streamData.audio_streams[0])}
+                      disabled={streamData.audio_streams.length === 0}
+                      className="px-6 py-2 bg-blue-500 hover:bg-blue-600 disabled:opacity-50 disabled:cursor-not-allowed text-white rounded-lg font-medium transition-all"
+                    >
+                      下载音频
+                    </button>
+                  </div>
+                ) : (
+                /* 清晰度选择 */
                 <div className="space-y-3 relative">
                   <label className="block text-lg font-semibold text-gray-700 dark:text-gray-300">
                     选择清晰度：
@@ -363,18 +492,21 @@ export default function Downloader() {
                   <CustomSelect
                     options={streamData.video_streams.map((videoStream, index) => ({
                       value: index,
-                      label: videoStream.description,
-                      description: videoStream.format ? `格式: ${videoStream.format}` : undefined,
+                      label: videoStream.label || videoStream.description,
+                      description: videoStream.width && videoStream.height
+                        ? `${videoStream.width}x${videoStream.height} · ${videoStream.description}`
+                        : videoStream.format ? `格式: ${videoStream.format}` : undefined,
                       filesize: videoStream.filesize
                     }))}
                     onChange={(selectedIndex) => {
                       const selectedVideo = streamData.video_streams[selectedIndex as number];
-                      const selectedAudio = streamData.audio_streams[0];
+                      const selectedAudio = streamData.audio_streams[selectedAudioIndex] ?? streamData.audio_streams[0];
                       handleQualitySelect(selectedVideo, selectedAudio);
                     }}
                     placeholder="请选择视频清晰度"
                   />
                 </div>
+                )}
               </div>
 
               <div className="flex justify-end space-x-3 mt-6">
@@ -1,138 +1,106 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { QRCodeSVG } from 'qrcode.react';
+import { listen } from '@tauri-apps/api/event';
 import { useAppStore } from '../store/appStore';
-import { QrCodeData, LoginSuccessData, LoginStatus, UserProfile } from '../types/bilibili';
+import { QrCodeData, QrImage, QrRenderOptions, LoginStatus } from '../types/bilibili';
 import { cn } from '../lib/utils';
+import { formatError } from '../lib/tauri';
 
 interface LoginProps {
   onClose?: () => void;
 }
 
+// 二维码图片边长（像素）
+const QR_SIZE = 200;
+
 // 检查是否在 Tauri 环境中
 const isTauriAvailable = () => {
   return typeof window !== 'undefined' && (window as any).__TAURI_INTERNALS__;
 };
 
 export default function Login({ onClose }: LoginProps) {
-  const [qrData, setQrData] = useState<QrCodeData | null>(null);
+  const [qrImage, setQrImage] = useState<QrImage | null>(null);
   const [loginStatus, setLoginStatus] = useState<'idle' | 'loading' | 'polling' | 'success' | 'error'>('idle');
   const [statusMessage, setStatusMessage] = useState('');
-  const [pollInterval, setPollInterval] = useState<number | null>(null);
   
-  const { setLoginStatus: setGlobalLoginStatus } = useAppStore();
+  const { applyLoginInfo, loadProfiles } = useAppStore();
 
-  // 检查 Tauri 环境
+  // 订阅后端推送的扫码状态，然后开始登录
   useEffect(() => {
     if (!isTauriAvailable()) {
       setLoginStatus('error');
       setStatusMessage('请在 Tauri 应用中使用登录功能');
-    } else {
-      // 自动获取二维码
-      getQrCode();
+      return;
     }
-  }, []);
 
-  // 清理定时器
-  useEffect(() => {
-    return () => {
-      if (pollInterval) {
-        clearInterval(pollInterval);
+    let closeTimer: number | undefined;
+    const unlisten = listen<LoginStatus>('login-status', ({ payload }) => {
+      switch (payload.status) {
+        case 'waiting_scan':
+          setQrImage(payload.image);
+          setLoginStatus('polling');
+          setStatusMessage('请使用哔哩哔哩App扫码登录');
+          break;
+        case 'scanned':
+          setStatusMessage('扫码成功！请在手机上确认登录');
+          break;
+        case 'expired':
+          if (payload.regenerating) {
+            setStatusMessage('二维码已过期，正在刷新...');
+          } else {
+            setQrImage(null);
+            setLoginStatus('error');
+            setStatusMessage('二维码已过期');
+          }
+          break;
+        case 'confirmed':
+          applyLoginInfo(payload.login_info);
+          loadProfiles();
+          setLoginStatus('success');
+          setStatusMessage('登录成功！正在跳转...');
+          closeTimer = window.setTimeout(() => {
+            onClose?.();
+          }, 1500);
+          break;
+        case 'failed':
+          setLoginStatus('error');
+          setStatusMessage(`登录失败：${formatError(payload.error)}`);
+          break;
       }
-    };
-  }, [pollInterval]);
+    });
 
-  // 获取二维码
-  const getQrCode = async () => {
-    if (!isTauriAvailable()) {
-      setLoginStatus('error');
-      setStatusMessage('Tauri 环境不可用，请运行 pnpm tauri dev');
-      return;
-    }
+    unlisten.then(() => startLogin());
+
+    return () => {
+      window.clearTimeout(closeTimer);
+      unlisten.then(fn => fn());
+      invoke('cancel_qr_login').catch(() => {});
+    };
+  }, []);
 
+  // 开始扫码登录，之后的状态由 login-status 事件推送
+  const startLogin = async () => {
     try {
+      setQrImage(null);
       setLoginStatus('loading');
       setStatusMessage('正在获取登录二维码...');
       
-      const qrCodeData: QrCodeData = await invoke('get_login_qr_code');
-      
-      setQrData(qrCodeData);
-      setLoginStatus('polling');
-      setStatusMessage('请使用哔哩哔哩App扫码登录');
-      
-      // 开始轮询登录状态
-      startPolling(qrCodeData.qrcode_key);
+      const options: QrRenderOptions = { size: QR_SIZE, ec_level: 'm' };
+      await invoke<QrCodeData>('start_qr_login', { options });
     } catch (error) {
       setLoginStatus('error');
-      setStatusMessage(`获取二维码失败：${error}`);
+      setStatusMessage(`获取二维码失败：${formatError(error)}`);
     }
   };
 
-  // 开始轮询登录状态
-  const startPolling = (qrcodeKey: string) => {
-    const interval = window.setInterval(async () => {
-      try {
-        const result: LoginSuccessData = await invoke('poll_login_status', { qrcodeKey });
-        const code = result.poll_data.code;
-        
-        switch (code) {
-          case LoginStatus.PENDING:
-            setStatusMessage('等待扫码中...');
-            break;
-          case LoginStatus.SCANNED:
-            setStatusMessage('扫码成功！请在手机上确认登录');
-            break;
-          case LoginStatus.SUCCESS:
-            setStatusMessage('登录成功！正在获取用户信息...');
-            setLoginStatus('success');
-            clearInterval(interval);
-            
-            // 获取用户信息
-            try {
-              const userProfile = await invoke<UserProfile>('get_user_info', { cookies: result.cookies });
-              setGlobalLoginStatus(true, userProfile, result.cookies);
-              setStatusMessage('登录成功！正在跳转...');
-            } catch (error) {
-              console.error('获取用户信息失败:', error);
-              // 即使获取用户信息失败，也使用默认信息登录
-              const defaultProfile = { name: '用户', avatar: '', mid: 0, vip_type: 0 };
-              setGlobalLoginStatus(true, defaultProfile, result.cookies);
-              setStatusMessage('登录成功！正在跳转...');
-            }
-            
-            setTimeout(() => {
-              onClose?.();
-            }, 1500);
-            break;
-          case LoginStatus.EXPIRED:
-            setStatusMessage('二维码已过期');
-            setLoginStatus('error');
-            clearInterval(interval);
-            break;
-          default:
-            setStatusMessage(`状态: ${result.poll_data.message}`);
-            break;
-        }
-      } catch (error) {
-        setStatusMessage('网络连接错误，请检查网络');
-        setLoginStatus('error');
-        clearInterval(interval);
-      }
-    }, 2000);
-
-    setPollInterval(interval);
-  };
-
   // 重新获取二维码
   const refreshQrCode = () => {
-    if (pollInterval) {
-      clearInterval(pollInterval);
-      setPollInterval(null);
+    if (!isTauriAvailable()) {
+      setLoginStatus('error');
+      setStatusMessage('Tauri 环境不可用，请运行 pnpm tauri dev');
+      return;
     }
-    setQrData(null);
-    setLoginStatus('idle');
-    setStatusMessage('');
-    getQrCode();
+    startLogin();
   };
 
   return (
@@ -219,13 +187,13 @@ export default function Login({ onClose }: LoginProps) {
             {/* 二维码区域 */}
             <div className="text-center">
               <div className="relative inline-block p-6 bg-gradient-to-br from-gray-50 to-gray-100 dark:from-gray-800 dark:to-gray-700 rounded-3xl shadow-inner">
-                {qrData ? (
+                {qrImage ? (
                   <div className="relative">
-                    <QRCodeSVG
-                      value={qrData.url}
-                      size={200}
-                      level="M"
-                      includeMargin={true}
+                    <img
+                      src={qrImage.svg}
+                      width={QR_SIZE}
+                      height={QR_SIZE}
+                      alt="登录二维码"
                       className="rounded-2xl shadow-sm"
                     />
                   </div>
@@ -1,11 +1,12 @@
 import { useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { useAppStore } from '../store/appStore';
+import { formatError } from '../lib/tauri';
 
 export default function TestPage() {
   const [testResult, setTestResult] = useState<string>('');
   const [isRunning, setIsRunning] = useState<boolean>(false);
-  const { isLoggedIn, cookies, downloads, userProfile } = useAppStore();
+  const { isLoggedIn, downloads, userProfile } = useAppStore();
 
   const runDownloadTest = async () => {
     setIsRunning(true);
@@ -18,7 +19,6 @@ export default function TestPage() {
       
       // 2. 检查登录状态
       setTestResult(prev => prev + `登录状态: ${isLoggedIn ? '已登录' : '未登录'}\n`);
-      setTestResult(prev => prev + `Cookies: ${cookies ? '已获取 (长度:' + cookies.length + ')' : '无数据'}\n`);
       
       // 3. 检查下载队列
       setTestResult(prev => prev + `下载队列: ${downloads.length} 项任务\n`);
@@ -28,7 +28,7 @@ export default function TestPage() {
         return;
       }
       
-      if (!isLoggedIn || !cookies) {
+      if (!isLoggedIn) {
         setTestResult(prev => prev + '请先登录哔哩哔哩账号\n');
         return;
       }
@@ -47,8 +47,7 @@ export default function TestPage() {
         setTestResult(prev => prev + '\n测试视频流获取...\n');
         const streamData = await invoke('get_video_streams', {
           videoId: (videoData as any).bvid,
-          cid: (videoData as any).pages[0].cid,
-          cookies
+          cid: (videoData as any).pages[0].cid
         });
         
         setTestResult(prev => prev + `视频流获取成功: ${(streamData as any).video_streams.length} 个质量选项\n`);
@@ -70,11 +69,11 @@ export default function TestPage() {
         setTestResult(prev => prev + '\n所有测试通过！系统功能正常，可以开始下载。\n');
         
       } catch (videoError) {
-        setTestResult(prev => prev + `视频相关测试失败: ${videoError}\n`);
+        setTestResult(prev => prev + `视频相关测试失败: ${formatError(videoError)}\n`);
       }
       
     } catch (error) {
-      setTestResult(prev => prev + `测试过程出现错误: ${error}\n`);
+      setTestResult(prev => prev + `测试过程出现错误: ${formatError(error)}\n`);
     } finally {
       setIsRunning(false);
     }
@@ -177,14 +176,14 @@ export default function TestPage() {
               </div>
               
               {/* Cookies状态 */}
-              <div className={`p-4 rounded-2xl ${cookies ? 'bg-blue-50 dark:bg-blue-900/20 border border-blue-200 dark:border-blue-800' : 'bg-gray-50 dark:bg-gray-700 border border-gray-200 dark:border-gray-600'}`}>
+              <div className={`p-4 rounded-2xl ${isLoggedIn ? 'bg-blue-50 dark:bg-blue-900/20 border border-blue-200 dark:border-blue-800' : 'bg-gray-50 dark:bg-gray-700 border border-gray-200 dark:border-gray-600'}`}>
                 <div className="flex items-center space-x-3">
                   <div>
                     <p className="font-semibold text-gray-900 dark:text-white">
-                      认证信息: {cookies ? `已获取 (${cookies.length} 字符)` : '未获取'}
+                      认证信息: {isLoggedIn ? '已保存（仅后端持有）' : '未获取'}
                     </p>
                     <p className="text-sm text-gray-600 dark:text-gray-400">
-                      {cookies ? '可以进行下载操作' : '需要登录获取认证信息'}
+                      {isLoggedIn ? '可以进行下载操作' : '需要登录获取认证信息'}
                     </p>
                   </div>
                 </div>
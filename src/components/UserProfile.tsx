@@ -1,11 +1,28 @@
+import { useEffect, useState } from 'react';
 import { useAppStore } from '../store/appStore';
+import { formatError } from '../lib/tauri';
 
 interface UserProfileProps {
   onClose?: () => void;
 }
 
 export default function UserProfile({ onClose }: UserProfileProps) {
-  const { userProfile, isLoggedIn } = useAppStore();
+  const { userProfile, isLoggedIn, profiles, loadProfiles, switchProfile, removeProfile } = useAppStore();
+  const [profileError, setProfileError] = useState('');
+
+  useEffect(() => {
+    loadProfiles();
+  }, []);
+
+  // 切换或删除账号，失败时在账号列表下方提示
+  const handleProfileAction = async (action: () => Promise<void>) => {
+    try {
+      setProfileError('');
+      await action();
+    } catch (error) {
+      setProfileError(formatError(error));
+    }
+  };
 
   if (!isLoggedIn || !userProfile) {
     return (
@@ -135,6 +152,48 @@ export default function UserProfile({ onClose }: UserProfileProps) {
               </div>
             </div>
           </div>
+          {/* 已保存的账号 */}
+          {profiles.length > 1 && (
+            <div className="rounded-2xl p-6 border border-gray-200/50 dark:border-gray-700/50">
+              <h3 className="text-lg font-semibold text-gray-900 dark:text-white mb-4">切换账号</h3>
+              <div className="space-y-2">
+                {profiles.map(profile => (
+                  <div key={profile.id} className="flex items-center justify-between px-4 py-3 rounded-xl bg-gray-50 dark:bg-gray-700/50">
+                    <div>
+                      <p className="font-semibold text-gray-900 dark:text-white">{profile.name}</p>
+                      {profile.user_profile && (
+                        <p className="text-xs text-gray-500 dark:text-gray-400">
+                          UID: {profile.user_profile.mid}{profile.user_profile.vip_type === 1 ? ' · 大会员' : ''}
+                        </p>
+                      )}
+                    </div>
+                    {profile.active ? (
+                      <span className="text-sm font-semibold text-green-600 dark:text-green-400">当前账号</span>
+                    ) : (
+                      <div className="flex space-x-2">
+                        <button
+                          onClick={() => handleProfileAction(() => switchProfile(profile.id))}
+                          className="px-3 py-1 text-sm bg-blue-500 hover:bg-blue-600 text-white rounded-lg transition-colors"
+                        >
+                          切换
+                        </button>
+                        <button
+                          onClick={() => handleProfileAction(() => removeProfile(profile.id))}
+                          className="px-3 py-1 text-sm bg-gray-200 hover:bg-gray-300 dark:bg-gray-600 dark:hover:bg-gray-500 text-gray-700 dark:text-gray-200 rounded-lg transition-colors"
+                        >
+                          删除
+                        </button>
+                      </div>
+                    )}
+                  </div>
+                ))}
+              </div>
+              {profileError && (
+                <p className="mt-3 text-sm text-red-600 dark:text-red-400">{profileError}</p>
+              )}
+            </div>
+          )}
+
           {/* 底部按钮 */}
           <div className="flex justify-center">
             <button
@@ -1,3 +1,5 @@
+import type { DilidiliError, ErrorCode } from '../types/bilibili';
+
 /**
  * 检查是否在 Tauri 环境中
  */
@@ -27,4 +29,41 @@ export const safeTauriCall = async <T>(
         console.error('Tauri API call failed:', error);
         throw error;
     }
-}; 
\ No newline at end of file
+};
+
+// 常见错误的提示，其余错误直接显示后端返回的 message
+const ERROR_HINTS: Partial<Record<ErrorCode, string>> = {
+    not_logged_in: '请先登录',
+    rate_limited: '请求过于频繁，请稍后再试',
+    risk_control: '触发了B站风控，请稍后再试',
+    access_denied: '没有访问权限',
+    not_found: '视频不存在或已被删除',
+    region_restricted: '该视频在当前地区不可用',
+    video_invisible: '视频不可见',
+    video_under_review: '视频正在审核中',
+    video_private: '视频仅UP主自己可见',
+    charge_only: '该视频为充电专属视频',
+    network: '网络连接失败',
+    ffmpeg_not_found: '未找到 ffmpeg，请先安装',
+    cancelled: '任务已取消',
+    credentials_locked: '登录数据已加密，请先输入口令解锁',
+};
+
+/**
+ * 判断是否为后端返回的结构化错误
+ */
+export const isDilidiliError = (error: unknown): error is DilidiliError => {
+    return typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+};
+
+/**
+ * 把 invoke 抛出的错误转换为可展示的文字
+ */
+export const formatError = (error: unknown): string => {
+    if (!isDilidiliError(error)) {
+        return String(error);
+    }
+    const hint = ERROR_HINTS[error.code];
+    const message = hint ? `${hint}（${error.message}）` : error.message;
+    return error.retryable ? `${message}，可稍后重试` : message;
+};
@@ -1,35 +1,36 @@
 import { create } from 'zustand';
 import { invoke } from '@tauri-apps/api/core';
-import type { UserProfile, DownloadItem, StoredLoginData } from '../types/bilibili';
+import type { UserProfile, DownloadItem, LoginInfo, ProfileSummary } from '../types/bilibili';
 
 interface AppState {
     isLoggedIn: boolean;
     userProfile: UserProfile | null;
-    cookies: string;
     downloads: DownloadItem[];
-    setLoginStatus: (isLoggedIn: boolean, userProfile?: UserProfile, cookies?: string) => void;
+    profiles: ProfileSummary[];
+    setLoginStatus: (isLoggedIn: boolean, userProfile?: UserProfile) => void;
+    applyLoginInfo: (loginInfo: LoginInfo) => void;
     addDownloadItem: (item: DownloadItem) => void;
     updateDownloadProgress: (id: string, progress: number) => void;
     updateDownloadStatus: (id: string, status: 'pending' | 'downloading' | 'completed' | 'failed') => void;
     logout: () => void;
     loadLoginData: () => Promise<void>;
-    saveLoginData: () => Promise<void>;
+    loadProfiles: () => Promise<void>;
+    switchProfile: (profileId: string) => Promise<void>;
+    removeProfile: (profileId: string) => Promise<void>;
 }
 
 export const useAppStore = create<AppState>((set, get) => ({
     isLoggedIn: false,
     userProfile: null,
-    cookies: '',
     downloads: [],
-    setLoginStatus: async (isLoggedIn, userProfile, cookies) => {
-        set({ isLoggedIn, userProfile, cookies: cookies || '' });
-
-        // 如果登录成功，自动保存登录数据
-        if (isLoggedIn && userProfile && cookies) {
-            const { saveLoginData } = get();
-            await saveLoginData();
-        }
-    },
+    profiles: [],
+    // 登录数据由后端保存，前端只记录登录状态
+    setLoginStatus: (isLoggedIn, userProfile) => set({ isLoggedIn, userProfile: userProfile || null }),
+    // 后端已经保存过的登录信息（扫码登录成功、切换账号），只更新状态
+    applyLoginInfo: (loginInfo) => set({
+        isLoggedIn: true,
+        userProfile: loginInfo.user_profile || null
+    }),
     addDownloadItem: (item) => set((state) => ({
         downloads: [...state.downloads, item]
     })),
@@ -46,28 +47,25 @@ export const useAppStore = create<AppState>((set, get) => ({
     logout: async () => {
         set({
             isLoggedIn: false,
-            userProfile: null,
-            cookies: ''
+            userProfile: null
         });
 
         try {
             await invoke('clear_login_data');
         } catch (error) {
         }
+        await get().loadProfiles();
     },
     loadLoginData: async () => {
         try {
-            const loginData = await invoke<StoredLoginData | null>('load_login_data');
+            const loginInfo = await invoke<LoginInfo | null>('load_login_data');
 
-            if (loginData) {
-                const isExpired = Date.now() - loginData.login_time > 7 * 24 * 60 * 60 * 1000;
+            if (loginInfo) {
+                // 有 refresh_token 时由后端自动刷新 cookie，不按登录时间过期
+                const isExpired = !loginInfo.has_refresh_token && Date.now() - loginInfo.login_time > 7 * 24 * 60 * 60 * 1000;
 
                 if (!isExpired) {
-                    set({
-                        isLoggedIn: true,
-                        userProfile: loginData.user_profile || null,
-                        cookies: loginData.cookies
-                    });
+                    get().applyLoginInfo(loginInfo);
                 } else {
                     await invoke('clear_login_data');
                 }
@@ -77,20 +75,25 @@ export const useAppStore = create<AppState>((set, get) => ({
             console.error('加载登录数据失败:', error);
         }
     },
-    saveLoginData: async () => {
-        const { isLoggedIn, userProfile, cookies } = get();
-
-        if (isLoggedIn && userProfile && cookies) {
-            const loginData: StoredLoginData = {
-                cookies,
-                user_profile: userProfile,
-                login_time: Date.now()
-            };
-
-            try {
-                await invoke('save_login_data', { loginData });
-            } catch (error) {
-            }
+    loadProfiles: async () => {
+        try {
+            const profiles = await invoke<ProfileSummary[]>('list_profiles');
+            set({ profiles });
+        } catch (error) {
+            console.error('加载账号列表失败:', error);
+        }
+    },
+    // 切换当前账号，之后的请求使用该账号的 cookie
+    switchProfile: async (profileId) => {
+        const loginInfo = await invoke<LoginInfo>('switch_profile', { profileId });
+        get().applyLoginInfo(loginInfo);
+        await get().loadProfiles();
+    },
+    removeProfile: async (profileId) => {
+        const profiles = await invoke<ProfileSummary[]>('remove_profile', { profileId });
+        set({ profiles });
+        if (!profiles.some(profile => profile.active)) {
+            set({ isLoggedIn: false, userProfile: null });
         }
     }
 })); 
\ No newline at end of file
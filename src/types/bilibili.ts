@@ -13,21 +13,29 @@ export interface PollData {
     timestamp?: number;
 }
 
-// 登录成功数据
-export interface LoginSuccessData {
-    poll_data: PollData;
-    cookies: string;
+// 二维码纠错等级
+export type QrEcLevel = 'l' | 'm' | 'q' | 'h';
+
+// 二维码渲染选项，size 为图片边长（像素）
+export interface QrRenderOptions {
+    size: number;
+    ec_level: QrEcLevel;
 }
 
-// 登录状态枚举
-export enum LoginStatus {
-    PENDING = 86101,     // 未扫码
-    SCANNED = 86090,     // 已扫码未确认
-    SUCCESS = 0,         // 登录成功
-    EXPIRED = 86038,     // 二维码已过期
-    ERROR = -1,          // 错误状态
+// 后端渲染的二维码图片，可直接作为 <img> 的 src
+export interface QrImage {
+    svg: string;
+    png: string;
 }
 
+// 后端扫码登录推送的状态（login-status 事件）
+export type LoginStatus =
+    | { status: 'waiting_scan'; qrcode: QrCodeData; image: QrImage } // 新二维码，等待扫码
+    | { status: 'scanned' }                                   // 已扫码未确认
+    | { status: 'expired'; regenerating: boolean }            // 二维码已过期，regenerating 为 true 时后端会推送新二维码
+    | { status: 'confirmed'; login_info: LoginInfo }          // 登录成功，后端已保存登录数据
+    | { status: 'failed'; error: DilidiliError };
+
 // 用户基本信息
 export interface UserProfile {
     name: string;
@@ -36,6 +44,24 @@ export interface UserProfile {
     vip_type: number;
 }
 
+// 后端 parse_link 解析出的链接目标
+export type BiliTarget =
+    | { type: 'video'; id: string; page?: number; start_time?: number }
+    | { type: 'episode'; ep_id: number }
+    | { type: 'season'; season_id: number }
+    | { type: 'media'; media_id: number }
+    | { type: 'space'; mid: number }
+    | { type: 'favorites'; media_id: number }
+    | { type: 'collection'; mid: number; season_id: number }
+    | { type: 'series'; mid: number; series_id: number }
+    | { type: 'live'; room_id: number };
+
+// convert_video_id 返回的 av 号与 BV 号
+export interface VideoIds {
+    aid: number;
+    bvid: string;
+}
+
 // 视频信息相关接口
 export interface VideoPage {
     cid: number;
@@ -59,22 +85,53 @@ export interface VideoData {
     owner_info: VideoOwner;
     duration: number;
     pages: VideoPage[];
+    pubdate?: number;
     author?: string;
 }
 
 // 视频流信息
+export type VideoCodec = 'AVC' | 'HEVC' | 'AV1' | 'UNKNOWN';
+
 export interface VideoStream {
     quality: number;
     format: string;
     description: string;
+    label: string;
+    codec: VideoCodec;
+    codecs?: string;
+    codecid?: number;
+    width?: number;
+    height?: number;
+    frame_rate?: number;
+    bandwidth?: number;
+    mime_type?: string;
     url?: string;
+    backup_urls?: string[];
     filesize?: number;
+    // durl 分段，非空时音视频已封装在一起
+    segments?: StreamSegment[];
 }
 
+export interface StreamSegment {
+    url: string;
+    backup_urls?: string[];
+    size?: number;
+    length?: number;
+}
+
+export type AudioCodec = 'AAC' | 'EAC3' | 'FLAC' | 'UNKNOWN';
+
 export interface AudioStream {
     quality: number;
     format: string;
+    description: string;
+    codec: AudioCodec;
+    codecs?: string;
+    codecid?: number;
+    bandwidth?: number;
+    mime_type?: string;
     url?: string;
+    backup_urls?: string[];
     filesize?: number;
 }
 
@@ -92,15 +149,169 @@ export interface DownloadItem {
     // 可选的额外下载信息
     videoData?: VideoData;
     selectedQuality?: {
-        video: VideoStream;
-        audio: AudioStream;
+        video?: VideoStream;
+        audio?: AudioStream;
+        mode?: DownloadMode;
+        cid?: number;
     };
 }
 
-export interface StoredLoginData {
-    cookies: string;
+// 下载进度事件（download-progress）
+export interface DownloadProgress {
+    task_id: string;
+    stream: 'video' | 'audio';
+    downloaded: number;
+    total?: number;
+    speed: number;
+    eta?: number;
+    progress: number;
+    overall: number;
+    message: string;
+}
+
+// 后端下载队列
+export interface DownloadOptions {
+    segmented: boolean;
+    connections: number;
+    min_chunk_size: number;
+    segment_retries: number;
+}
+
+export type DownloadMode = 'both' | 'video_only' | 'audio_only';
+
+export interface DownloadRequest {
+    video_data: VideoData;
+    video_stream?: VideoStream;
+    audio_stream?: AudioStream;
+    cid?: number;
+    mode?: DownloadMode;
+    options?: Partial<DownloadOptions>;
+    // 使用指定账号获取流地址，为空时使用当前账号
+    profile_id?: string | null;
+}
+
+export type JobState = 'queued' | 'running' | 'paused' | 'failed' | 'done';
+
+// 后端命令返回的错误，code 为稳定的错误代码
+export type ErrorCode =
+    | 'network'
+    | 'http_status'
+    | 'not_found'
+    | 'rate_limited'
+    | 'not_logged_in'
+    | 'risk_control'
+    | 'access_denied'
+    | 'region_restricted'
+    | 'video_invisible'
+    | 'video_under_review'
+    | 'video_private'
+    | 'charge_only'
+    | 'api_error'
+    | 'parse'
+    | 'filesystem'
+    | 'ffmpeg_not_found'
+    | 'ffmpeg'
+    | 'cancelled'
+    | 'invalid_input'
+    | 'credentials_locked'
+    | 'unknown';
+
+export interface DilidiliError {
+    code: ErrorCode;
+    message: string;
+    retryable: boolean;
+    status?: number | null;   // HTTP 状态码
+    api_code?: number | null; // B站接口返回的 code
+}
+
+// 下载任务（download-status 事件）
+export interface DownloadJob {
+    id: string;
+    title: string;
+    state: JobState;
+    progress: number;
+    error?: DilidiliError | null;
+    output?: string;
+    created_at: number;
+    request: DownloadRequest;
+}
+
+// 批量加入分P的结果（enqueue_pages），failed 为未能加入队列的分P
+export interface EnqueuePagesResult {
+    jobs: DownloadJob[];
+    failed: { page: number; part: string; error: DilidiliError }[];
+}
+
+// 自动选流规则与应用设置（settings.json）
+export type SelectionFallback = 'lowest' | 'best' | 'fail';
+
+export interface SelectionRules {
+    max_height?: number;
+    codec_order: VideoCodec[];
+    prefer_high_fps: boolean;
+    premium_audio: boolean;
+    max_size?: number;
+    fallback: SelectionFallback;
+}
+
+export interface AppSettings {
+    selection: SelectionRules;
+    // 为空时使用 <下载目录>/DILIDILI
+    output_dir?: string;
+    // 可用占位符：{title} {bvid} {aid} {author} {mid} {page} {part} {quality} {codec} {pubdate}，/ 表示子目录
+    filename_template: string;
+    page_template: string;
+    // 所有 API 请求共享的速率上限（每秒请求数）
+    requests_per_second: number;
+    proxy: ProxySettings;
+}
+
+// 保存的账号（list_profiles），不含 cookie
+export interface ProfileSummary {
+    id: string;
+    name: string;
+    user_profile?: UserProfile | null;
+    login_time: number;
+    active: boolean;
+}
+
+// 登录凭据的加密密钥来源：machine 由本机标识派生，passphrase 由用户口令派生
+export type CredentialMode = 'machine' | 'passphrase';
+
+export interface CredentialStatus {
+    mode: CredentialMode;
+    // 口令模式下尚未解锁，需要调用 unlock_credentials
+    locked: boolean;
+}
+
+export type ProxyScheme = 'http' | 'https' | 'socks5';
+
+// 代理设置，API 请求和 CDN 媒体流分别开关
+export interface ProxySettings {
+    scheme: ProxyScheme;
+    host: string;
+    port: number;
+    username?: string;
+    password?: string;
+    // 不走代理的主机，如 "localhost"、".example.com"、"192.168.0.0/16"
+    bypass: string[];
+    api: boolean;
+    media: boolean;
+}
+
+// 风控冷却事件（api-cooldown），冷却期间下载队列暂停启动新任务
+export interface ApiCooldown {
+    active: boolean;
+    remaining_secs: number;
+    reason: string;
+}
+
+// 当前账号的登录信息，cookie 和 refresh_token 只保存在后端
+export interface LoginInfo {
     user_profile?: UserProfile;
     login_time: number;
+    // 有 refresh_token 时后端定时刷新 cookie
+    has_refresh_token: boolean;
 }
 
 // FFmpeg和导出相关类型